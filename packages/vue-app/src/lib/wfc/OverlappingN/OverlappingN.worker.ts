@@ -1,5 +1,4 @@
 import { IterationResult } from '@unstoppablecarl/wfc-js'
-import { makeMulberry32 } from '../../util/mulberry32.ts'
 import { deserializeWFCRuleset, type SerializedWFCRuleset } from '../WFCRuleset.ts'
 import { ModelType, ModelTypeFactory, type OverlappingNOptions } from './OverlappingNModel.ts'
 
@@ -55,7 +54,6 @@ export type OverlappingNWorkerOptions = {
   modelType: ModelType,
   serializedRuleset: SerializedWFCRuleset,
   settings: Omit<OverlappingNOptions, 'ruleset'> & {
-    seed: number,
     maxAttempts: number,
     maxRevertsPerAttempt: number,
     previewInterval: number,
@@ -82,8 +80,6 @@ ctx.onmessage = async (e: MessageEvent<OverlappingNWorkerOptions>) => {
       ...settings,
     })
 
-    const mulberry32 = makeMulberry32(settings.seed)
-
     let totalReverts = 0
 
     for (let attempt = 1; attempt <= settings.maxAttempts; attempt++) {
@@ -100,7 +96,7 @@ ctx.onmessage = async (e: MessageEvent<OverlappingNWorkerOptions>) => {
 
       while (attemptActive) {
         stepCount++
-        const result = model.singleIteration(mulberry32)
+        const result = model.singleIteration()
         model.syncVisuals()
 
         if (result === IterationResult.SUCCESS) {
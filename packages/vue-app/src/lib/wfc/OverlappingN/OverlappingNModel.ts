@@ -1,4 +1,4 @@
-import { IterationResult, type RNG } from '@unstoppablecarl/wfc-js'
+import { IterationResult } from '@unstoppablecarl/wfc-js'
 import type { WFCRuleset } from '../WFCRuleset.ts'
 import { makeOverlappingNJS } from './OverlappingNModelJS.ts'
 import { makeOverlappingNModelWasm } from './OverlappingNModelWasm.ts'
@@ -13,13 +13,14 @@ export type OverlappingNOptions = {
   startCoordY: number,
   maxSnapShots: number,
   snapshotIntervalPercent: number,
+  seed: number,
   avgColor: number,
   palette: Uint32Array,
   contradictionColor: number,
 }
 
 export type OverlappingNModel = {
-  singleIteration: (rng: RNG) => IterationResult,
+  singleIteration: () => IterationResult,
   clear: () => void,
   isGenerationComplete: () => boolean,
   getFilledCount: () => number,
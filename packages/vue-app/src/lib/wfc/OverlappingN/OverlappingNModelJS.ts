@@ -1,4 +1,5 @@
 import { makeWFCModel } from '@unstoppablecarl/wfc-js'
+import { makeMulberry32 } from '../../util/mulberry32.ts'
 import { makeWFCPixelBuffer } from '../WFCPixelBuffer.ts'
 import type { OverlappingNModel, OverlappingNModelCreator, OverlappingNOptions } from './OverlappingNModel.ts'
 
@@ -10,6 +11,7 @@ export const makeOverlappingNJS: OverlappingNModelCreator = async (
     startCoordBias,
     startCoordX,
     startCoordY,
+    seed,
     ruleset,
     palette,
     avgColor,
@@ -31,6 +33,9 @@ export const makeOverlappingNJS: OverlappingNModelCreator = async (
     startCoordY,
   })
 
+  // unlike the wasm model, the JS one draws from a PRNG passed to each iteration
+  const rng = makeMulberry32(seed)
+
   const buffer = makeWFCPixelBuffer({
     palette,
     T,
@@ -45,6 +50,7 @@ export const makeOverlappingNJS: OverlappingNModelCreator = async (
 
   return {
     ...model,
+    singleIteration: () => model.singleIteration(rng),
     N: ruleset.N,
     syncVisuals: () => buffer.updateCells(model.getWave(), model.getObserved(), model.getChanges()),
     getImageBuffer: () => buffer.getVisualBuffer(),
@@ -1,10 +1,9 @@
-import init, { WFCModel } from '@unstoppablecarl/wfc-rust'
+import init, { Backtracking, CellSelection, GridShape, PatternChoice, WFCModel } from '@unstoppablecarl/wfc-rust'
 import wasmUrl from '@unstoppablecarl/wfc-rust/rust_wfc_bg.wasm?url'
 
 import { makeWFCPixelBuffer } from '../WFCPixelBuffer.ts'
 import type { OverlappingNModel, OverlappingNOptions } from './OverlappingNModel.ts'
 
-export type RNG = () => number
 export const makeOverlappingNModelWasm = async (
   {
     ruleset,
@@ -16,6 +15,7 @@ export const makeOverlappingNModelWasm = async (
     startCoordY,
     maxSnapShots,
     snapshotIntervalPercent,
+    seed,
     avgColor,
     palette,
     contradictionColor,
@@ -30,17 +30,26 @@ export const makeOverlappingNModelWasm = async (
   const model = new WFCModel(
     width,
     height,
+    1,
     T,
     propagator.weights,
     propagator.data,
     propagator.offsets,
     propagator.lengths,
+    GridShape.Square,
+    periodicOutput,
     periodicOutput,
     startCoordBias,
     startCoordX,
     startCoordY,
+    CellSelection.MinEntropy,
+    PatternChoice.WeightedRandom,
+    1,
+    Backtracking.Snapshot,
     maxSnapShots,
     snapshotIntervalPercent / 100,
+    64,
+    BigInt(Math.trunc(seed)),
   )
   const buffer = makeWFCPixelBuffer({
     palette,
@@ -72,7 +81,7 @@ export const makeOverlappingNModelWasm = async (
   return {
     ruleset,
     syncVisuals: () => buffer.updateCells(getWave(), getObserved(), getChanges()),
-    singleIteration: (rng: RNG) => model.single_iteration_with_snapshots(rng()),
+    singleIteration: () => model.single_iteration_with_snapshots(),
     clear: () => {
       buffer.clear()
       model.clear()
@@ -1,3 +1,4 @@
+#[cfg(feature = "wasm")]
 #[macro_export]
 macro_rules! log {
     ( $( $t:tt )* ) => {
@@ -5,23 +6,46 @@ macro_rules! log {
     }
 }
 
+// Native builds are libraries too, so logging must not write to the caller's
+// stdout. The arguments are still checked.
+#[cfg(not(feature = "wasm"))]
+#[macro_export]
+macro_rules! log {
+    ( $( $t:tt )* ) => {
+        { let _ = format_args!( $( $t )* ); }
+    }
+}
+
+#[cfg(feature = "wasm")]
 mod utils;
 #[macro_use]
 mod wfc_model;
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
 // Re-export the model so wasm-bindgen can see it at the top level
 pub use wfc_model::WFCModel;
 pub use wfc_model::IterationResult;
+pub use wfc_model::RunSummary;
+pub use wfc_model::BoundaryKind;
+pub use wfc_model::{AttemptRecord, RestartKind, SnapshotKind};
+
+// Pure Rust API
+pub use wfc_model::{
+    axial_to_offset, offset_to_axial, Backtracking, BoundaryMode, CellSelection, Direction, DirectionIndex, EdgeLabel, GraphTopology,
+    GridDirection, GridShape, HexDirection, MaskMode, MooreDirection, PatternChoice, PatternIndex, RestartPolicy, SideSpec,
+    SnapshotPolicy, Solver, VoxelDirection, WfcConfig, WfcConfigBuilder, WfcError, WorkBudget,
+};
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+#[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
 pub fn main_js() {
     utils::set_panic_hook();
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
-}
\ No newline at end of file
+}
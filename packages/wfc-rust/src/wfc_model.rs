@@ -1,33 +1,60 @@
-use crate::wfc_model::cell::Cell;
-use crate::wfc_model::cell::CellIndex;
-use crate::wfc_model::cell_collapsed_collection::CellCollapsedCollection;
-use crate::wfc_model::cell_collection::CellCollection;
-use crate::wfc_model::compatible::Compatible;
-use crate::wfc_model::direction::DIRECTIONS;
-use crate::wfc_model::dirty_cells::DirtyCells;
-use crate::wfc_model::entropy_tracker::EntropyTracker;
-use pattern_collection::PatternIndex;
-use crate::wfc_model::propagator::Propagator;
-use crate::wfc_model::spatial_priority::SpatialPriority;
-use crate::wfc_model::wave::Wave;
-use std::f64;
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+mod boundary;
 mod cell;
 mod cell_collapsed_collection;
 mod cell_collection;
+mod cell_selector;
 mod compatible;
+mod config;
+mod constraints;
 mod direction;
 mod dirty_cells;
 mod entropy_tracker;
+mod error;
+mod graph_topology;
+mod grid_topology;
+mod hex_topology;
+mod mask;
+mod nogoods;
+mod observation_queue;
+mod pattern_chooser;
 mod pattern_bitset;
 mod pattern_collection;
 mod propagator;
+mod restart;
+mod rng;
+mod snapshot_history;
+mod solver;
 mod spatial_priority;
+mod topology;
+mod trail;
+mod voxel_topology;
 mod wave;
-
-#[wasm_bindgen]
-#[derive(Clone, Copy, PartialEq)]
+mod work_budget;
+
+pub use boundary::{BoundaryMode, SideSpec};
+pub use cell_selector::CellSelection;
+pub use config::{WfcConfig, WfcConfigBuilder};
+pub use direction::{Direction, DirectionIndex, GridDirection};
+pub use error::WfcError;
+pub use graph_topology::{EdgeLabel, GraphTopology};
+pub use grid_topology::MooreDirection;
+pub use hex_topology::{axial_to_offset, offset_to_axial, HexDirection};
+pub use mask::MaskMode;
+pub use pattern_chooser::PatternChoice;
+pub use pattern_collection::PatternIndex;
+pub use restart::RestartPolicy;
+pub use snapshot_history::SnapshotPolicy;
+pub use solver::Solver;
+pub use topology::GridShape;
+pub use trail::Backtracking;
+pub use voxel_topology::VoxelDirection;
+pub use work_budget::WorkBudget;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum IterationResult {
     REVERT,
     SUCCESS,
@@ -35,417 +62,385 @@ pub enum IterationResult {
     FAIL,
 }
 
-#[derive(Clone)]
-pub struct WFCState {
-    pub wave: Wave,
-    pub compatible: Compatible,
-    pub entropy_tracker: EntropyTracker,
-    pub observed: CellCollection<i32>,
-    pub dirty_cells: DirtyCells,
-    pub stack: Vec<(CellIndex, PatternIndex)>,
+/// Wasm-side selector for [`BoundaryMode`]; `Fixed` takes its pattern separately.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BoundaryKind {
+    Open,
+    Periodic,
+    Fixed,
+    EdgeCompatible,
 }
 
-#[derive(Clone)]
-pub struct WaveSnapshot {
-    wave_data: Vec<u64>,
-    cells_collapsed_indices: Vec<CellIndex>,
-    tried_pattern: PatternIndex,
-    target_cell: CellIndex,
-    last_snapshot_progress: f64,
+impl BoundaryKind {
+    fn to_mode(self, pattern: usize) -> BoundaryMode {
+        match self {
+            BoundaryKind::Open => BoundaryMode::Open,
+            BoundaryKind::Periodic => BoundaryMode::Periodic,
+            BoundaryKind::Fixed => BoundaryMode::Fixed(PatternIndex::new(pattern)),
+            BoundaryKind::EdgeCompatible => BoundaryMode::EdgeCompatible,
+        }
+    }
 }
 
-impl WFCState {
-    pub fn ban(&mut self, cell_idx: CellIndex, pattern_idx: PatternIndex) {
-        if !self.wave.is_candidate(cell_idx, pattern_idx) {
-            return;
-        }
+/// Outcome of [`Solver::run`] and [`Solver::run_until_complete`].
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RunSummary {
+    /// `SUCCESS` or `FAIL` when the run ended on its own, `STEP` or `REVERT`
+    /// when the step or work budget ran out first.
+    pub result: IterationResult,
+    pub steps: usize,
+    pub reverts: usize,
+    pub restarts: usize,
+}
 
-        self.wave.eliminate_candidate(cell_idx, pattern_idx);
-        self.dirty_cells.mark_dirty(cell_idx);
-        self.entropy_tracker.ban_pattern(cell_idx, pattern_idx);
+/// One attempt of [`Solver::run_with_restarts`].
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AttemptRecord {
+    /// The seed the attempt ran with. The first attempt continues from the
+    /// solver's state, so this is the seed the solver was built or reseeded with.
+    pub seed: u64,
+
+    /// `SUCCESS` or `FAIL`, or `STEP` or `REVERT` when the attempt ran out of steps.
+    pub result: IterationResult,
+    pub steps: usize,
+    pub reverts: usize,
+}
+
+/// Wasm-side selector for [`RestartPolicy`]; its numbers are passed separately.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RestartKind {
+    Fixed,
+    Luby,
+    Geometric,
+}
 
-        if self.entropy_tracker.pattern_determined(cell_idx) {
-            let remaining = self.wave.find_remaining_pattern(cell_idx);
-            self.observed[cell_idx] = remaining;
+impl RestartKind {
+    fn to_policy(self, attempts: usize, unit: usize, factor: f64) -> RestartPolicy {
+        match self {
+            RestartKind::Fixed => RestartPolicy::Fixed { attempts },
+            RestartKind::Luby => RestartPolicy::Luby { unit, attempts },
+            RestartKind::Geometric => RestartPolicy::Geometric {
+                initial: unit,
+                factor,
+                attempts,
+            },
         }
+    }
+}
 
-        self.stack.push((cell_idx, pattern_idx));
+/// Wasm-side selector for [`SnapshotPolicy`]; `Decisions` takes its spacing separately.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SnapshotKind {
+    Progress,
+    Decisions,
+    Exponential,
+}
+
+impl SnapshotKind {
+    fn to_policy(self, every: usize) -> SnapshotPolicy {
+        match self {
+            SnapshotKind::Progress => SnapshotPolicy::Progress,
+            SnapshotKind::Decisions => SnapshotPolicy::Decisions(every),
+            SnapshotKind::Exponential => SnapshotPolicy::Exponential,
+        }
     }
 }
 
-#[wasm_bindgen]
+/// JS facade over [`Solver`]. Takes the flat propagator arrays produced by the
+/// JS ruleset and exposes raw buffer pointers for zero-copy reads.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct WFCModel {
-    width: usize,
-    height: usize,
-    n_cells: usize,
-    periodic: bool,
-    max_snapshots: usize,
-    snapshot_interval_percent: f64,
-
-    cell: Cell,
-    cells_collapsed: CellCollapsedCollection,
-    history: Vec<WaveSnapshot>,
-    propagator: Propagator,
-    spatial_priority: SpatialPriority,
-
-    state: WFCState,
-
-    generation_complete: bool,
-    last_snapshot_progress: f64,
-    to_ban_queue: Vec<(CellIndex, PatternIndex)>,
-
-    t_count: usize,
+    solver: Solver,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl WFCModel {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         width: usize,
         height: usize,
+        depth: usize,
         t_count: usize,
         weights: Vec<f64>,
         prop_data: Vec<i32>,
         prop_offsets: Vec<i32>,
         prop_lengths: Vec<i32>,
-        periodic: bool,
+        shape: GridShape,
+        periodic_x: bool,
+        periodic_y: bool,
         start_bias: f64,
         start_x: f64,
         start_y: f64,
+        selection: CellSelection,
+        pattern_choice: PatternChoice,
+        temperature: f64,
+        backtracking: Backtracking,
         max_snapshots: usize,
         snapshot_interval_percent: f64,
-    ) -> Self {
-        let n_cells = width * height;
-        let propagator = Propagator::new(prop_data, prop_offsets, prop_lengths, t_count);
-
-        let state = WFCState {
-            wave: Wave::new(n_cells, t_count),
-            compatible: Compatible::new(n_cells, t_count, &propagator),
-            entropy_tracker: EntropyTracker::new(n_cells, t_count, weights),
-            observed: CellCollection::new_with_value(n_cells, -1),
-            dirty_cells: DirtyCells::new(n_cells),
-            stack: Vec::with_capacity(n_cells * t_count),
-        };
-
-        Self {
-            width,
-            height,
-            n_cells,
-            periodic,
-            max_snapshots,
-            snapshot_interval_percent,
-            cell: Cell::new(width),
-            cells_collapsed: CellCollapsedCollection::new(n_cells),
-            history: Vec::with_capacity(max_snapshots),
-            propagator,
-            spatial_priority: SpatialPriority::new(width, height, start_bias, start_x, start_y),
-            state,
-            t_count,
-            generation_complete: false,
-            last_snapshot_progress: 0.0,
-            to_ban_queue: Vec::with_capacity(1024),
-        }
-    }
-
-    fn take_snapshot(&mut self, i: CellIndex, t: PatternIndex) {
-        if self.max_snapshots == 0 {
-            return;
-        }
-        let current_progress = self.filled_percent();
-        let diff = current_progress - self.last_snapshot_progress;
-
-        // Only take a snapshot if we've moved past the interval threshold
-        if diff < self.snapshot_interval_percent && !self.history.is_empty() {
-            return;
+        max_nogoods: usize,
+        seed: u64,
+    ) -> Result<WFCModel, WfcError> {
+        if weights.len() != t_count {
+            return Err(WfcError::PatternCountMismatch {
+                expected: t_count,
+                actual: weights.len(),
+            });
         }
 
-        // Maintain the maximum history limit to preserve memory
-        if self.history.len() >= self.max_snapshots {
-            self.history.remove(0);
-        }
+        let config = WfcConfig::builder(width, height)
+            .weights(weights)
+            .shape(shape)
+            .depth(depth)
+            .propagator_arrays(prop_data, prop_offsets, prop_lengths)
+            .periodic_x(periodic_x)
+            .periodic_y(periodic_y)
+            .start_bias(start_bias, start_x, start_y)
+            .selection(selection)
+            .pattern_choice(pattern_choice)
+            .temperature(temperature)
+            .backtracking(backtracking)
+            .max_snapshots(max_snapshots)
+            .snapshot_interval_percent(snapshot_interval_percent)
+            .max_nogoods(max_nogoods)
+            .seed(seed)
+            .build()?;
+
+        Ok(Self {
+            solver: Solver::new(config),
+        })
+    }
 
-        let snapshot = WaveSnapshot {
-            wave_data: self.state.wave.clone_data(),
-            cells_collapsed_indices: self.cells_collapsed.get_uncollapsed_cells().to_vec(),
-            tried_pattern: t,
-            target_cell: i,
-            last_snapshot_progress: self.last_snapshot_progress,
-        };
+    pub fn propagate(&mut self) -> bool {
+        self.solver.propagate()
+    }
 
-        self.history.push(snapshot);
-        self.last_snapshot_progress = current_progress;
+    pub fn revert(&mut self) -> bool {
+        self.solver.revert()
     }
 
-    pub fn propagate(&mut self) -> bool {
-        while let Some((cell_idx, pattern_idx)) = self.state.stack.pop() {
-            let coords = self.cell.get_coords(cell_idx);
-            let (x1, y1) = (coords.0, coords.1);
-
-            for &d in &DIRECTIONS {
-                let info = d.info();
-                let neighbor_coords = self.wrap_coords(x1 + info.dx, y1 + info.dy);
-
-                if let Some((x2, y2)) = neighbor_coords {
-                    let neighbor_cell = self.cell.get_index(x2, y2);
-                    let opp_dir = info.opposite;
-
-                    // Grouping references to avoid multiple field lookups
-                    let state = &mut self.state;
-                    let queue = &mut self.to_ban_queue;
-
-                    self.propagator
-                        .for_each_compatible_pattern(pattern_idx, d, |t2| {
-                            let new_count = state.compatible.decrement(neighbor_cell, t2, opp_dir);
-
-                            if new_count == 0 && state.wave.is_candidate(neighbor_cell, t2) {
-                                queue.push((neighbor_cell, t2));
-                            }
-                        });
-                }
-            }
-
-            if !self.process_ban_queue() {
-                return false;
-            }
-        }
-        true
+    pub fn single_iteration_with_snapshots(&mut self) -> IterationResult {
+        self.solver.step()
     }
 
-    fn process_ban_queue(&mut self) -> bool {
-        let mut i = 0;
-        while i < self.to_ban_queue.len() {
-            let (c_idx, p_idx) = self.to_ban_queue[i];
+    /// Steps up to `max_steps` times without returning to JS in between.
+    pub fn run(&mut self, max_steps: usize, restart_on_fail: bool) -> RunSummary {
+        self.solver.run(max_steps, restart_on_fail)
+    }
 
-            if self.state.wave.is_candidate(c_idx, p_idx) {
-                self.state.ban(c_idx, p_idx);
+    pub fn run_until_complete(&mut self, max_attempts: usize) -> RunSummary {
+        self.solver.run_until_complete(max_attempts)
+    }
 
-                if self.state.entropy_tracker.has_no_possible_patterns(c_idx) {
-                    self.to_ban_queue.clear();
-                    return false;
-                }
-            }
-            i += 1;
-        }
+    /// `unit` is the step budget of the first attempt, unused by `RestartKind::Fixed`.
+    /// `factor` is only read for `RestartKind::Geometric`.
+    pub fn run_with_restarts(
+        &mut self,
+        kind: RestartKind,
+        max_attempts: usize,
+        unit: usize,
+        factor: f64,
+    ) -> Result<RunSummary, WfcError> {
+        self.solver.run_with_restarts(kind.to_policy(max_attempts, unit, factor))
+    }
 
-        self.to_ban_queue.clear();
-        true
+    /// Only used with `Backtracking::Snapshot`. `every` is only read for
+    /// `SnapshotKind::Decisions`. Pass 0 as `max_bytes` for no size limit.
+    pub fn set_snapshot_policy(&mut self, kind: SnapshotKind, every: usize, max_bytes: usize) {
+        self.solver.set_snapshot_policy(kind.to_policy(every), max_bytes);
     }
 
-    fn collapse_cell(&mut self, cell: CellIndex, chosen_t: PatternIndex) {
-        // We use a local buffer to avoid the borrow checker "Boss"
-        let mut local_to_ban = Vec::with_capacity(64);
+    /// Only used with `Backtracking::Snapshot`. 1 stores every snapshot in full.
+    pub fn set_snapshot_keyframe_interval(&mut self, interval: usize) {
+        self.solver.set_snapshot_keyframe_interval(interval);
+    }
 
-        // 1. Ask the state's wave which patterns must go
-        self.state
-            .wave
-            .collapse_to_pattern(cell, chosen_t, |p_idx| {
-                local_to_ban.push(p_idx);
-            });
+    pub fn get_attempt_count(&self) -> usize {
+        self.solver.attempt_log().len()
+    }
 
-        // 2. Tell the state to ban them one by one
-        for p_idx in local_to_ban {
-            self.state.ban(cell, p_idx);
-        }
+    pub fn get_attempt(&self, index: usize) -> Option<AttemptRecord> {
+        self.solver.attempt_log().get(index).copied()
     }
 
-    pub fn revert(&mut self) -> bool {
-        if let Some(s) = self.history.pop() {
-            // 1. Restore Wave
-            self.state.wave.set_data(&s);
+    /// Works until `deadline_ms` (compared against `Date.now()`) or until the
+    /// pop/ban budget is spent. Pass 0 for no pop or ban limit.
+    pub fn step_for(&mut self, max_pops: usize, max_bans: usize, deadline_ms: f64) -> RunSummary {
+        let unlimited = |n: usize| if n == 0 { usize::MAX } else { n };
+        let budget = WorkBudget {
+            max_pops: unlimited(max_pops),
+            max_bans: unlimited(max_bans),
+        };
 
-            // 2. Reset counts and Entropy
-            self.state.compatible.reset(&self.propagator);
-            self.state.entropy_tracker.reset();
-            self.state.observed.fill(-1);
+        self.solver.step_for(budget, || now_ms() >= deadline_ms)
+    }
 
-            // 3. The "Heavy Lifter": Rebuild state from the Wave
-            self.rebuild_state_from_wave();
+    pub fn is_propagation_pending(&self) -> bool {
+        self.solver.is_propagation_pending()
+    }
 
-            // 4. Restore the uncollapsed cell list
-            self.cells_collapsed.reset_from_snapshot(&s.cells_collapsed_indices);
+    pub fn set_cell(&mut self, x: usize, y: usize, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_cell(x, y, PatternIndex::new(pattern))
+    }
 
-            // 5. Cleanup and re-apply the triggering ban
-            self.to_ban_queue.clear();
-            self.state.stack.clear();
-            self.last_snapshot_progress = s.last_snapshot_progress;
-            self.state.dirty_cells.mark_all_dirty();
+    pub fn ban_at(&mut self, x: usize, y: usize, pattern: usize) -> Result<(), WfcError> {
+        self.solver.ban_at(x, y, PatternIndex::new(pattern))
+    }
 
-            self.state.ban(s.target_cell, s.tried_pattern);
+    pub fn restrict_cell(&mut self, x: usize, y: usize, allowed: Vec<usize>) -> Result<(), WfcError> {
+        let allowed: Vec<PatternIndex> = allowed.into_iter().map(PatternIndex::new).collect();
 
-            return true;
-        }
+        self.solver.restrict_cell(x, y, &allowed)
+    }
 
-        false
+    pub fn regenerate_region(&mut self, x: usize, y: usize, w: usize, h: usize) -> Result<(), WfcError> {
+        self.solver.regenerate_region(x, y, w, h)
     }
 
-    fn rebuild_state_from_wave(&mut self) {
-        let n_cells = self.n_cells;
-        let t_count = self.t_count;
+    /// `pattern` is only read for `BoundaryKind::Fixed`.
+    pub fn set_boundary_x(&mut self, kind: BoundaryKind, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_boundary_x(kind.to_mode(pattern))
+    }
 
-        for i in 0..n_cells {
-            let cell_idx = CellIndex { base: i };
+    pub fn set_boundary_y(&mut self, kind: BoundaryKind, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_boundary_y(kind.to_mode(pattern))
+    }
 
-            // We only need to propagate cells that have had patterns removed
-            if self.state.wave.is_fully_undetermined(cell_idx, &self.state.entropy_tracker) {
-                continue;
-            }
+    pub fn set_boundary_z(&mut self, kind: BoundaryKind, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_boundary_z(kind.to_mode(pattern))
+    }
 
-            for t in 0..t_count {
-                let pattern_idx = PatternIndex { base: t };
-                if !self.state.wave.is_candidate(cell_idx, pattern_idx) {
-                    // If the pattern is banned in the wave,
-                    // update the neighbor counts
-                    self.manually_propagate_ban(cell_idx, pattern_idx);
-                    self.state.entropy_tracker.ban_pattern(cell_idx, pattern_idx);
-                }
-            }
+    pub fn set_edge_patterns(&mut self, patterns: Vec<usize>) -> Result<(), WfcError> {
+        let patterns: Vec<PatternIndex> = patterns.into_iter().map(PatternIndex::new).collect();
 
-            if self.state.entropy_tracker.pattern_determined(cell_idx) {
-                let p = self.state.wave.find_remaining_pattern(cell_idx);
-                self.state.observed[cell_idx] = p;
-            }
-        }
+        self.solver.set_edge_patterns(&patterns)
     }
 
-    fn manually_propagate_ban(&mut self, cell_idx: CellIndex, pattern_idx: PatternIndex) {
-        let coords = self.cell.get_coords(cell_idx);
-        let (x1, y1) = (coords.0, coords.1);
-
-        for &d in &DIRECTIONS {
-            let info = d.info();
-            let neighbor_coords = self.wrap_coords(x1 + info.dx, y1 + info.dy);
+    /// Only `allowed` patterns may appear along `side`. An empty list lifts the requirement.
+    pub fn set_side_allowed(&mut self, side: Direction, allowed: Vec<usize>) -> Result<(), WfcError> {
+        let spec = if allowed.is_empty() {
+            SideSpec::Any
+        } else {
+            SideSpec::Allowed(allowed.into_iter().map(PatternIndex::new).collect())
+        };
 
-            if let Some((x2, y2)) = neighbor_coords {
-                let neighbor_cell = self.cell.get_index(x2, y2);
-                let opp_dir = info.opposite;
+        self.solver.set_side(side, spec)
+    }
 
-                let propagator = &self.propagator;
-                let compatible = &mut self.state.compatible;
+    pub fn set_side_fixed(&mut self, side: Direction, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_side(side, SideSpec::Fixed(PatternIndex::new(pattern)))
+    }
 
-                // Update neighbor counts for all patterns supported by the banned pattern
-                propagator.for_each_compatible_pattern(pattern_idx, d, |t2| {
-                    compatible.decrement(neighbor_cell, t2, opp_dir);
-                });
-            }
-        }
+    /// Masks out the cells where `active` is 0. Their neighbors see an open edge.
+    pub fn set_mask_open(&mut self, active: Vec<u8>) -> Result<(), WfcError> {
+        self.solver.set_mask(mask_flags(&active), MaskMode::Open)
     }
 
-    #[inline(always)]
-    fn wrap_coords(&self, x: i32, y: i32) -> Option<(i32, i32)> {
-        let (mut nx, mut ny) = (x, y);
-        let (w, h) = (self.width as i32, self.height as i32);
+    /// Masks out the cells where `active` is 0. Their neighbors see `pattern`.
+    pub fn set_mask_void(&mut self, active: Vec<u8>, pattern: usize) -> Result<(), WfcError> {
+        self.solver.set_mask(mask_flags(&active), MaskMode::Void(PatternIndex::new(pattern)))
+    }
 
-        if self.periodic {
-            nx = (nx % w + w) % w;
-            ny = (ny % h + h) % h;
-            Some((nx, ny))
-        } else if nx >= 0 && ny >= 0 && nx < w && ny < h {
-            Some((nx, ny))
-        } else {
-            None
-        }
+    pub fn clear_mask(&mut self) {
+        self.solver.clear_mask();
     }
 
-    pub fn single_iteration_with_snapshots(&mut self, rng_val: f64) -> IterationResult {
-        let target = self.find_observe_target(rng_val);
-
-        match target {
-            None => {
-                self.generation_complete = true;
-                IterationResult::SUCCESS
-            }
-            Some(i) => {
-                if self.state.entropy_tracker.has_no_possible_patterns(i) {
-                    return if self.revert() {
-                        IterationResult::REVERT
-                    } else {
-                        IterationResult::FAIL
-                    };
-                }
-
-                let chosen_t =
-                    self.state
-                        .wave
-                        .get_random_pattern(i, rng_val, &self.state.entropy_tracker);
-
-                self.take_snapshot(i, chosen_t);
-                self.collapse_cell(i, chosen_t);
-
-                if self.propagate() {
-                    self.cells_collapsed.refresh(&self.state.entropy_tracker);
-                    IterationResult::STEP
-                } else if self.revert() {
-                    IterationResult::REVERT
-                } else {
-                    IterationResult::FAIL
-                }
-            }
-        }
+    pub fn clear_constraints(&mut self) {
+        self.solver.clear_constraints();
     }
 
-    fn find_observe_target(&self, rng_val: f64) -> Option<CellIndex> {
-        let mut min_score = f64::MAX;
-        let mut min_idx = None;
-        let noise_scale = 1e-6;
+    pub fn reseed(&mut self, seed: u64) {
+        self.solver.reseed(seed);
+    }
 
-        for &idx in self.cells_collapsed.get_uncollapsed_cells() {
-            let entropy = self.state.entropy_tracker.get_cell_entropy(idx);
-            let score = entropy + self.spatial_priority.get_bias(idx) + (noise_scale * rng_val);
+    pub fn get_rng_state(&self) -> u64 {
+        self.solver.rng_state()
+    }
 
-            if score < min_score {
-                min_score = score;
-                min_idx = Some(idx);
-            }
-        }
-        min_idx
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.solver.set_rng_state(state);
     }
 
     pub fn wave_ptr(&self) -> *const u64 {
-        self.state.wave.as_ptr()
+        self.solver.wave_ptr()
     }
     pub fn observed_ptr(&self) -> *const i32 {
-        self.state.observed.data.as_ptr()
+        self.solver.observed_ptr()
     }
     pub fn entropies_ptr(&self) -> *const f64 {
-        self.state.entropy_tracker.entropies_ptr()
+        self.solver.entropies_ptr()
     }
 
     pub fn clear(&mut self) {
-        self.generation_complete = false;
-        self.state.wave.fill(1);
-        self.state.observed.fill(-1);
-        self.history.clear();
-        self.cells_collapsed.reset(self.n_cells);
-        self.state.dirty_cells.clear();
-        self.state.compatible.reset(&self.propagator);
-        self.state.entropy_tracker.reset();
-        self.state.stack.clear();
+        self.solver.clear();
     }
 
     pub fn filled_percent(&self) -> f64 {
-        (self.cells_collapsed.collapsed_count() as f64) / (self.n_cells as f64)
+        self.solver.filled_percent()
     }
 
     pub fn get_changes(&mut self) -> Vec<i32> {
-        self.state.dirty_cells.flush_to_js()
+        self.solver.take_changes()
     }
 
     pub fn is_generation_complete(&self) -> bool {
-        self.generation_complete
+        self.solver.is_generation_complete()
     }
 
     pub fn get_filled_count(&self) -> usize {
-        self.cells_collapsed.collapsed_count()
+        self.solver.filled_count()
+    }
+
+    pub fn get_learned_nogood_count(&self) -> usize {
+        self.solver.learned_nogood_count()
     }
 
     pub fn get_total_cells(&self) -> usize {
-        self.n_cells
+        self.solver.total_cells()
     }
 
+    /// On wasm this is the size of the whole linear memory. Native builds have no
+    /// equivalent, so they report an estimate from the solver's own buffers.
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
     pub fn get_total_memory_usage_bytes(&self) -> usize {
         let pages = core::arch::wasm32::memory_size(0);
-        let bytes = pages * 65536;
 
-        bytes
+        pages * 65536
+    }
+
+    #[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+    pub fn get_total_memory_usage_bytes(&self) -> usize {
+        self.get_estimated_memory_usage_bytes()
     }
+
+    /// Sum of the buffers owned by the solver: wave, compatible counts,
+    /// entropy tracking and the snapshot history.
+    pub fn get_estimated_memory_usage_bytes(&self) -> usize {
+        self.solver.memory_usage_bytes()
+    }
+}
+
+// JS passes masks as bytes; any nonzero value marks an active cell.
+fn mask_flags(active: &[u8]) -> Vec<bool> {
+    active.iter().map(|&a| a != 0).collect()
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
 }
@@ -0,0 +1,165 @@
+use crate::wfc_model::cell::{Cell, CellIndex};
+use crate::wfc_model::constraints::Constraints;
+use crate::wfc_model::direction::{Direction, DirectionIndex, DIRECTIONS};
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::propagator::Propagator;
+use crate::wfc_model::topology::{Axis, Topology};
+
+/// How the output behaves past its edges along one axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundaryMode {
+    /// Cells past the edge impose no constraint.
+    Open,
+
+    /// The edge wraps around to the opposite edge.
+    Periodic,
+
+    /// Cells past the edge behave as if they held this pattern.
+    Fixed(PatternIndex),
+
+    /// Only patterns marked as edge-compatible may touch the edge.
+    EdgeCompatible,
+}
+
+/// Patterns required in the outermost row or column on one side of the output.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum SideSpec {
+    /// No requirement.
+    #[default]
+    Any,
+
+    /// Every cell along the side must hold one of these patterns.
+    Allowed(Vec<PatternIndex>),
+
+    /// Every cell along the side holds this pattern, e.g. a ground row.
+    Fixed(PatternIndex),
+}
+
+impl SideSpec {
+    pub fn allows(&self, pattern: PatternIndex) -> bool {
+        match self {
+            SideSpec::Any => true,
+            SideSpec::Allowed(patterns) => patterns.contains(&pattern),
+            SideSpec::Fixed(fixed) => *fixed == pattern,
+        }
+    }
+
+    pub fn patterns(&self) -> &[PatternIndex] {
+        match self {
+            SideSpec::Any => &[],
+            SideSpec::Allowed(patterns) => patterns,
+            SideSpec::Fixed(fixed) => std::slice::from_ref(fixed),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Boundary {
+    pub x: BoundaryMode,
+    pub y: BoundaryMode,
+    pub z: BoundaryMode,
+
+    // Indexed by pattern, used by `BoundaryMode::EdgeCompatible`.
+    pub edge_patterns: Vec<bool>,
+
+    // Indexed by `Direction`.
+    pub sides: [SideSpec; 4],
+}
+
+impl Boundary {
+    #[inline(always)]
+    pub fn periodic_x(&self) -> bool {
+        self.x == BoundaryMode::Periodic
+    }
+
+    #[inline(always)]
+    pub fn periodic_y(&self) -> bool {
+        self.y == BoundaryMode::Periodic
+    }
+
+    #[inline(always)]
+    pub fn periodic_z(&self) -> bool {
+        self.z == BoundaryMode::Periodic
+    }
+
+    /// The bans implied by the non-periodic modes and the side specs, for every
+    /// cell along the edges.
+    pub fn collect_bans(
+        &self,
+        topology: &dyn Topology,
+        cell: &Cell,
+        width: usize,
+        height: usize,
+        depth: usize,
+        propagator: &Propagator,
+    ) -> Constraints {
+        let mut bans = Constraints::new();
+        let t_count = self.edge_patterns.len();
+
+        // A cell may leave the output in several directions, e.g. at a corner or on a hex grid.
+        for i in 0..topology.cell_count() {
+            let cell_idx = CellIndex { base: i };
+
+            for d_idx in 0..topology.direction_count() {
+                let d = DirectionIndex { base: d_idx };
+                let mode = match topology.exit_axis(cell_idx, d) {
+                    Some(Axis::X) => self.x,
+                    Some(Axis::Y) => self.y,
+                    Some(Axis::Z) => self.z,
+                    None => continue,
+                };
+                if matches!(mode, BoundaryMode::Open | BoundaryMode::Periodic) {
+                    continue;
+                }
+
+                for t in 0..t_count {
+                    let pattern_idx = PatternIndex { base: t };
+                    let allowed = match mode {
+                        BoundaryMode::Open | BoundaryMode::Periodic => true,
+                        BoundaryMode::Fixed(outside) => propagator.is_compatible(pattern_idx, d, outside),
+                        BoundaryMode::EdgeCompatible => self.edge_patterns[t],
+                    };
+
+                    if !allowed {
+                        bans.push(cell_idx, pattern_idx);
+                    }
+                }
+            }
+        }
+
+        for &side in &DIRECTIONS {
+            let spec = &self.sides[side as usize];
+            if *spec == SideSpec::Any {
+                continue;
+            }
+
+            for cell_idx in edge_cells(cell, width, height, depth, side) {
+                for t in 0..t_count {
+                    let pattern_idx = PatternIndex { base: t };
+
+                    if !spec.allows(pattern_idx) {
+                        bans.push(cell_idx, pattern_idx);
+                    }
+                }
+            }
+        }
+
+        bans
+    }
+}
+
+/// The cells touching the `side` edge of the output, in every z layer.
+pub fn edge_cells(cell: &Cell, width: usize, height: usize, depth: usize, side: Direction) -> Vec<CellIndex> {
+    let (w, h) = (width as i32, height as i32);
+    let mut cells = Vec::new();
+
+    for z in 0..depth as i32 {
+        match side {
+            Direction::West => cells.extend((0..h).map(|y| cell.get_index_3d(0, y, z))),
+            Direction::East => cells.extend((0..h).map(|y| cell.get_index_3d(w - 1, y, z))),
+            Direction::North => cells.extend((0..w).map(|x| cell.get_index_3d(x, 0, z))),
+            Direction::South => cells.extend((0..w).map(|x| cell.get_index_3d(x, h - 1, z))),
+        }
+    }
+    cells
+}
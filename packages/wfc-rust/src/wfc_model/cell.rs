@@ -1,23 +1,22 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct CellIndex {
     pub base: usize,
 }
 
-impl CellIndex {
-    #[inline(always)]
-    pub fn to_coords(&self, width: usize) -> (usize, usize) {
-        (self.base % width, self.base / width)
-    }
-}
-
 pub struct Cell {
     width: usize,
+
+    // Cells per z layer.
+    layer: usize,
 }
 
 impl Cell {
     #[inline(always)]
-    pub fn new(width: usize) -> Self {
-        Self { width }
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            layer: width * height,
+        }
     }
     #[inline(always)]
     pub fn get_coords(&self, index: CellIndex) -> (i32, i32) {
@@ -31,4 +30,23 @@ impl Cell {
 
         CellIndex { base }
     }
+
+    #[inline(always)]
+    pub fn get_coords_3d(&self, index: CellIndex) -> (i32, i32, i32) {
+        let i = index.base;
+        let in_layer = i % self.layer;
+
+        (
+            (in_layer % self.width) as i32,
+            (in_layer / self.width) as i32,
+            (i / self.layer) as i32,
+        )
+    }
+
+    #[inline(always)]
+    pub fn get_index_3d(&self, x: i32, y: i32, z: i32) -> CellIndex {
+        let base = (x as usize) + (y as usize) * self.width + (z as usize) * self.layer;
+
+        CellIndex { base }
+    }
 }
@@ -1,74 +1,117 @@
 use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::cell_selector::CellSelector;
+use crate::wfc_model::dirty_cells::DirtyCells;
 use crate::wfc_model::entropy_tracker::EntropyTracker;
+use crate::wfc_model::observation_queue::{Key, ObservationQueue};
 
+/// The cells left to observe, ordered by a `CellSelector`.
 #[derive(Clone)]
 pub struct CellCollapsedCollection {
-    pub indices: Vec<CellIndex>,
-    pub count: usize,
+    queue: ObservationQueue,
     n_cells: usize,
+
+    // The cells taking part in generation, i.e. not masked out.
+    active: Vec<CellIndex>,
 }
 
 impl CellCollapsedCollection {
-    pub fn new(n_cells: usize) -> Self {
-        let indices = (0..n_cells)
-            .map(|n| {
-                let idx = CellIndex { base: n };
-
-                idx
-            })
-            .collect();
+    /// Starts out empty; call `reset` once entropies and the selector are ready.
+    pub fn new(active: Vec<CellIndex>, total_cells: usize) -> Self {
+        let n_cells = active.len();
 
         Self {
-            indices,
+            queue: ObservationQueue::new(total_cells),
             n_cells,
-            count: n_cells,
+            active,
         }
     }
 
-    pub fn get_uncollapsed_cells(&self) -> &[CellIndex] {
-        let active_slice = &self.indices[..self.count];
+    /// The cell with the lowest score, i.e. the next one to observe.
+    #[inline(always)]
+    pub fn next_target(&self) -> Option<CellIndex> {
+        self.queue.peek()
+    }
 
-        active_slice
+    pub fn get_uncollapsed_cells(&self) -> Vec<CellIndex> {
+        self.queue.cells().collect()
     }
 
-    pub fn reset(&mut self, n_cells: usize) {
-        self.count = n_cells;
+    pub fn reset(&mut self, entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) {
+        let active = std::mem::take(&mut self.active);
 
-        for i in 0..n_cells {
-            let idx = CellIndex { base: i };
+        self.reset_to(&active, entropy_tracker, selector);
+        self.active = active;
+    }
 
-            self.indices[i] = idx;
-        }
+    /// Cells taking part in generation.
+    pub fn active_count(&self) -> usize {
+        self.n_cells
     }
 
-    pub fn refresh(&mut self, entropy_tracker: &EntropyTracker) {
-        let mut j = 0;
-        let current_count = self.count;
+    /// Rescores the cells in `touched` and drops the ones with a single pattern
+    /// left. Cells without any stay queued so that the contradiction is found.
+    pub fn refresh(&mut self, touched: &mut DirtyCells, entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) {
+        let queue = &mut self.queue;
 
-        for i in 0..current_count {
-            let idx = self.indices[i];
-            let pattern_count = entropy_tracker.possible_pattern_count(idx);
+        touched.drain(|idx| {
+            if !queue.contains(idx) {
+                return;
+            }
 
-            if pattern_count > 1 {
-                self.indices[j] = idx;
-                j += 1;
+            if entropy_tracker.pattern_determined(idx) {
+                queue.remove(idx);
+            } else if selector.is_dynamic() {
+                queue.update(idx, key(idx, entropy_tracker, selector));
             }
+        });
+    }
+
+    /// Queues a cell again after bans on it were undone.
+    pub fn reopen(&mut self, cell: CellIndex, entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) {
+        if !self.queue.contains(cell) && entropy_tracker.possible_pattern_count(cell) > 1 {
+            self.queue.insert(cell, key(cell, entropy_tracker, selector));
         }
+    }
 
-        self.count = j;
+    /// Recomputes every score, e.g. after the selector was reseeded.
+    pub fn rescore(&mut self, entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) {
+        let cells = self.get_uncollapsed_cells();
+
+        self.reset_to(&cells, entropy_tracker, selector);
     }
 
     pub fn collapsed_count(&self) -> usize {
         // The number of collapsed cells is simply Total - Uncollapsed
-        let collapsed = self.n_cells - self.count;
+        self.n_cells - self.queue.len()
+    }
 
-        collapsed
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.queue.memory_usage_bytes() + self.active.capacity() * std::mem::size_of::<CellIndex>()
     }
 
-    pub fn reset_from_snapshot(&mut self, saved_indices: &[CellIndex]) {
-        let n = saved_indices.len();
+    /// Limits observation to `cells`, e.g. a region being regenerated.
+    pub fn reset_to(&mut self, cells: &[CellIndex], entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) {
+        self.reset_from_snapshot(cells, entropy_tracker, selector);
+    }
 
-        self.indices[..n].copy_from_slice(saved_indices);
-        self.count = n;
+    /// Rebuilds the queue from `saved_indices`, leaving out cells that are
+    /// already determined.
+    pub fn reset_from_snapshot(
+        &mut self,
+        saved_indices: &[CellIndex],
+        entropy_tracker: &EntropyTracker,
+        selector: &dyn CellSelector,
+    ) {
+        let entries = saved_indices
+            .iter()
+            .filter(|&&idx| !entropy_tracker.pattern_determined(idx))
+            .map(|&idx| (key(idx, entropy_tracker, selector), idx));
+
+        self.queue.rebuild(entries);
     }
 }
+
+#[inline(always)]
+fn key(cell: CellIndex, entropy_tracker: &EntropyTracker, selector: &dyn CellSelector) -> Key {
+    (selector.score(cell, entropy_tracker), selector.tie_break(cell))
+}
@@ -15,20 +15,20 @@ impl<T: Clone + Default> CellCollection<T> {
     }
 
     pub fn len(&self) -> usize {
-        let length = self.data.len();
-
-        length
+        self.data.len()
     }
 
     pub fn as_ptr(&self) -> *const T {
-        let ptr = self.data.as_ptr();
-
-        ptr
+        self.data.as_ptr()
     }
 
     pub fn fill(&mut self, value: T) {
         self.data.fill(value);
     }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<T>()
+    }
 }
 
 impl<T: Clone + Default> Index<CellIndex> for CellCollection<T> {
@@ -0,0 +1,235 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::wfc_model::cell::{Cell, CellIndex};
+use crate::wfc_model::cell_collection::CellCollection;
+use crate::wfc_model::entropy_tracker::EntropyTracker;
+use crate::wfc_model::rng::Rng;
+use crate::wfc_model::spatial_priority::SpatialPriority;
+
+/// Decides the order in which cells are observed: the uncollapsed cell with
+/// the lowest score goes next.
+pub trait CellSelector {
+    fn score(&self, cell: CellIndex, entropy_tracker: &EntropyTracker) -> f64;
+
+    /// Orders cells whose scores are equal, lowest first.
+    fn tie_break(&self, _cell: CellIndex) -> f64 {
+        0.0
+    }
+
+    /// Whether scores change as patterns are banned. Fixed orders are only
+    /// scored when a cell enters the queue.
+    fn is_dynamic(&self) -> bool;
+
+    /// Redraws per-attempt randomness. Called whenever generation starts over.
+    fn reseed(&mut self, _rng: &mut Rng) {}
+}
+
+/// The built-in [`CellSelector`]s.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CellSelection {
+    /// Lowest Shannon entropy plus the start bias.
+    MinEntropy,
+
+    /// Fewest remaining patterns (MRV) plus the start bias.
+    MinRemaining,
+
+    /// Row by row from the top left, layer by layer on voxel grids.
+    Scanline,
+
+    /// Ring by ring outwards from the start point.
+    Spiral,
+
+    /// Along a Hilbert curve over the grid, layer by layer on voxel grids.
+    Hilbert,
+
+    /// A random order, drawn anew for each attempt.
+    Random,
+}
+
+/// `start` is the fraction of the output size that `start_bias` and the spiral
+/// center on.
+pub fn build_selector(selection: CellSelection, size: [usize; 3], bias: f64, start: [f64; 3]) -> Box<dyn CellSelector> {
+    let n_cells = size[0] * size[1] * size[2];
+
+    match selection {
+        CellSelection::MinEntropy => Box::new(MinEntropy {
+            priority: SpatialPriority::new(size, bias, start),
+        }),
+        CellSelection::MinRemaining => Box::new(MinRemaining {
+            priority: SpatialPriority::new(size, bias, start),
+        }),
+        CellSelection::Scanline => Box::new(Scanline),
+        CellSelection::Spiral => Box::new(Spiral::new(size, start)),
+        CellSelection::Hilbert => Box::new(Hilbert::new(size)),
+        CellSelection::Random => Box::new(RandomOrder {
+            order: CellCollection::new_with_value(n_cells, 0.0),
+        }),
+    }
+}
+
+pub struct MinEntropy {
+    priority: SpatialPriority,
+}
+
+impl CellSelector for MinEntropy {
+    #[inline(always)]
+    fn score(&self, cell: CellIndex, entropy_tracker: &EntropyTracker) -> f64 {
+        entropy_tracker.get_cell_entropy(cell) + self.priority.get_bias(cell)
+    }
+
+    fn tie_break(&self, cell: CellIndex) -> f64 {
+        self.priority.get_noise(cell)
+    }
+
+    fn is_dynamic(&self) -> bool {
+        true
+    }
+
+    fn reseed(&mut self, rng: &mut Rng) {
+        self.priority.reseed_noise(rng);
+    }
+}
+
+pub struct MinRemaining {
+    priority: SpatialPriority,
+}
+
+impl CellSelector for MinRemaining {
+    #[inline(always)]
+    fn score(&self, cell: CellIndex, entropy_tracker: &EntropyTracker) -> f64 {
+        entropy_tracker.possible_pattern_count(cell) as f64 + self.priority.get_bias(cell)
+    }
+
+    fn tie_break(&self, cell: CellIndex) -> f64 {
+        self.priority.get_noise(cell)
+    }
+
+    fn is_dynamic(&self) -> bool {
+        true
+    }
+
+    fn reseed(&mut self, rng: &mut Rng) {
+        self.priority.reseed_noise(rng);
+    }
+}
+
+pub struct Scanline;
+
+impl CellSelector for Scanline {
+    // Cells are stored row-major, so the index is the scanline position.
+    fn score(&self, cell: CellIndex, _entropy_tracker: &EntropyTracker) -> f64 {
+        cell.base as f64
+    }
+
+    fn is_dynamic(&self) -> bool {
+        false
+    }
+}
+
+pub struct Spiral {
+    cell: Cell,
+    center: [i32; 3],
+}
+
+impl Spiral {
+    fn new(size: [usize; 3], start: [f64; 3]) -> Self {
+        let axis = |i: usize| ((size[i] - 1) as f64 * start[i]).round() as i32;
+
+        Self {
+            cell: Cell::new(size[0], size[1]),
+            center: [axis(0), axis(1), axis(2)],
+        }
+    }
+}
+
+impl CellSelector for Spiral {
+    // The ring index, plus the angle around the center as a fraction below 1.
+    fn score(&self, cell: CellIndex, _entropy_tracker: &EntropyTracker) -> f64 {
+        let (x, y, z) = self.cell.get_coords_3d(cell);
+        let (dx, dy, dz) = (x - self.center[0], y - self.center[1], z - self.center[2]);
+        let ring = dx.abs().max(dy.abs()).max(dz.abs());
+        let turn = ((dy as f64).atan2(dx as f64) + std::f64::consts::PI) / (2.0 * std::f64::consts::PI);
+
+        ring as f64 + turn * 0.5
+    }
+
+    fn is_dynamic(&self) -> bool {
+        false
+    }
+}
+
+pub struct Hilbert {
+    cell: Cell,
+    // Side of the power-of-two square the curve is laid over.
+    side: usize,
+}
+
+impl Hilbert {
+    fn new(size: [usize; 3]) -> Self {
+        Self {
+            cell: Cell::new(size[0], size[1]),
+            side: size[0].max(size[1]).next_power_of_two(),
+        }
+    }
+}
+
+impl CellSelector for Hilbert {
+    fn score(&self, cell: CellIndex, _entropy_tracker: &EntropyTracker) -> f64 {
+        let (x, y, z) = self.cell.get_coords_3d(cell);
+        let layer = z as usize * self.side * self.side;
+
+        (layer + hilbert_index(self.side, x as usize, y as usize)) as f64
+    }
+
+    fn is_dynamic(&self) -> bool {
+        false
+    }
+}
+
+// Distance of (x, y) along the Hilbert curve filling a `side` x `side` square,
+// where `side` is a power of two.
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+    let mut d = 0;
+    let mut s = side / 2;
+
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        d += s * s * ((3 * rx) ^ ry);
+
+        // Rotate the quadrant so the sub-curve starts and ends in the right corners
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    d
+}
+
+pub struct RandomOrder {
+    order: CellCollection<f64>,
+}
+
+impl CellSelector for RandomOrder {
+    #[inline(always)]
+    fn score(&self, cell: CellIndex, _entropy_tracker: &EntropyTracker) -> f64 {
+        self.order[cell]
+    }
+
+    fn is_dynamic(&self) -> bool {
+        false
+    }
+
+    fn reseed(&mut self, rng: &mut Rng) {
+        for value in self.order.data.iter_mut() {
+            *value = rng.next_f64();
+        }
+    }
+}
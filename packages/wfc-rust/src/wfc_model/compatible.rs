@@ -1,5 +1,5 @@
 use crate::wfc_model::cell::CellIndex;
-use crate::wfc_model::direction::{Direction, DIRECTIONS};
+use crate::wfc_model::direction::DirectionIndex;
 use crate::wfc_model::pattern_collection::PatternIndex;
 use crate::wfc_model::propagator::Propagator;
 
@@ -7,6 +7,7 @@ use crate::wfc_model::propagator::Propagator;
 pub struct Compatible {
     data: Vec<u16>,
     t_count: usize,
+    d_count: usize,
     cells_per_dir: usize,
 }
 
@@ -16,21 +17,22 @@ pub struct CompatibleIndex {
 }
 
 impl Compatible {
-    pub fn new(n_cells: usize, t_count: usize, propagator: &Propagator) -> Self {
-        let mut compatible = Self::new_empty(n_cells, t_count);
+    pub fn new(n_cells: usize, t_count: usize, d_count: usize, propagator: &Propagator) -> Self {
+        let mut compatible = Self::new_empty(n_cells, t_count, d_count);
 
         compatible.reset(propagator);
         compatible
     }
 
     #[inline(always)]
-    pub fn new_empty(n_cells: usize, t_count: usize) -> Self {
+    pub fn new_empty(n_cells: usize, t_count: usize, d_count: usize) -> Self {
         let cells_per_dir = n_cells * t_count;
-        let data = vec![0u16; cells_per_dir * 4];
+        let data = vec![0u16; cells_per_dir * d_count];
 
         Self {
             data,
             t_count,
+            d_count,
             cells_per_dir,
         }
     }
@@ -40,11 +42,11 @@ impl Compatible {
         &self,
         cell: CellIndex,
         pattern: PatternIndex,
-        direction: Direction,
+        direction: DirectionIndex,
     ) -> CompatibleIndex {
-        // Layout: [All North][All South][All East][All West]
+        // Layout: [All of direction 0][All of direction 1]...
         // This makes the counts for a specific direction contiguous in memory
-        let dir_offset = (direction as usize) * self.cells_per_dir;
+        let dir_offset = direction.base * self.cells_per_dir;
         let cell_offset = cell.base * self.t_count;
         let base = dir_offset + cell_offset + pattern.base;
 
@@ -55,13 +57,20 @@ impl Compatible {
     //     &mut self,
     //     cell: CellIndex,
     //     pattern: PatternIndex,
-    //     direction: Direction,
+    //     direction: DirectionIndex,
     //     value: u16,
     // ) {
     //     let index = self.get_index(cell, pattern, direction);
     //     self.data[index.base] = value;
     // }
 
+    #[inline(always)]
+    pub fn get(&self, cell: CellIndex, pattern: PatternIndex, direction: DirectionIndex) -> u16 {
+        let idx = self.get_index(cell, pattern, direction);
+
+        self.data[idx.base]
+    }
+
     pub fn decrement_by_index(&mut self, idx: CompatibleIndex) -> u16 {
         let current_val = self.data[idx.base];
 
@@ -77,20 +86,33 @@ impl Compatible {
         &mut self,
         cell: CellIndex,
         pattern: PatternIndex,
-        direction: Direction,
+        direction: DirectionIndex,
     ) -> u16 {
         let idx = self.get_index(cell, pattern, direction);
 
         self.decrement_by_index(idx)
     }
 
+    /// Undoes a `decrement`.
+    #[inline(always)]
+    pub fn increment(&mut self, cell: CellIndex, pattern: PatternIndex, direction: DirectionIndex) {
+        let idx = self.get_index(cell, pattern, direction);
+
+        self.data[idx.base] += 1;
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<u16>()
+    }
+
     pub fn reset(&mut self, propagator: &Propagator) {
         let t_count = self.t_count;
         let cells_per_dir = self.cells_per_dir;
 
         // Linear Memory Lanes: During propagation, you only look at one direction at a time.
         // By grouping by direction first, the CPU cache line (64 bytes) is filled with 32 contiguous u16 counts for the same direction.
-        for &d in &DIRECTIONS {
+        for d_idx in 0..self.d_count {
+            let d = DirectionIndex { base: d_idx };
             let mut dir_template = Vec::with_capacity(t_count);
 
             for t in 0..t_count {
@@ -101,7 +123,7 @@ impl Compatible {
             }
 
             // 2. Blast the template into the specific "lane" for this direction
-            let dir_start = (d as usize) * cells_per_dir;
+            let dir_start = d_idx * cells_per_dir;
             let dir_end = dir_start + cells_per_dir;
             let dir_lane = &mut self.data[dir_start..dir_end];
 
@@ -0,0 +1,570 @@
+use crate::wfc_model::boundary::{BoundaryMode, SideSpec};
+use crate::wfc_model::cell_selector::CellSelection;
+use crate::wfc_model::direction::{Direction, DirectionIndex, GridDirection};
+use crate::wfc_model::error::WfcError;
+use crate::wfc_model::graph_topology::GraphTopology;
+use crate::wfc_model::mask::{Mask, MaskMode};
+use crate::wfc_model::pattern_chooser::PatternChoice;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::snapshot_history::SnapshotPolicy;
+use crate::wfc_model::topology::{GridShape, Topology};
+use crate::wfc_model::trail::Backtracking;
+
+#[derive(Clone, Debug)]
+pub struct WfcConfig {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) depth: usize,
+    pub(crate) weights: Vec<f64>,
+    pub(crate) shape: GridShape,
+
+    // Replaces `shape` when set. The nodes are laid out as a single row.
+    pub(crate) graph: Option<GraphTopology>,
+    pub(crate) mask: Option<Mask>,
+
+    // Compatible neighbor patterns, indexed as [pattern][direction].
+    pub(crate) adjacency: Vec<Vec<Vec<PatternIndex>>>,
+    pub(crate) boundary_x: BoundaryMode,
+    pub(crate) boundary_y: BoundaryMode,
+    pub(crate) boundary_z: BoundaryMode,
+    pub(crate) edge_patterns: Vec<PatternIndex>,
+
+    // Indexed by `Direction`.
+    pub(crate) sides: [SideSpec; 4],
+    pub(crate) start_bias: f64,
+    pub(crate) start_x: f64,
+    pub(crate) start_y: f64,
+    pub(crate) start_z: f64,
+    pub(crate) selection: CellSelection,
+    pub(crate) pattern_choice: PatternChoice,
+    pub(crate) temperature: f64,
+    pub(crate) backtracking: Backtracking,
+    pub(crate) max_snapshots: usize,
+    pub(crate) snapshot_interval_percent: f64,
+    pub(crate) snapshot_policy: SnapshotPolicy,
+
+    // Total size of the kept snapshots, 0 for no limit.
+    pub(crate) max_snapshot_bytes: usize,
+    pub(crate) snapshot_keyframe_interval: usize,
+    pub(crate) max_nogoods: usize,
+    pub(crate) seed: u64,
+}
+
+impl WfcConfig {
+    pub fn builder(width: usize, height: usize) -> WfcConfigBuilder {
+        WfcConfigBuilder::new(width, height)
+    }
+
+    /// A solver over `graph` instead of a grid. Adjacency is given per edge
+    /// label, and node `i` is addressed as (`i`, 0). Boundary modes and side
+    /// specs do not apply.
+    pub fn graph_builder(graph: GraphTopology) -> WfcConfigBuilder {
+        let mut builder = WfcConfigBuilder::new(graph.node_count(), 1);
+
+        builder.config.graph = Some(graph);
+        builder
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn direction_count(&self) -> usize {
+        match &self.graph {
+            Some(graph) => graph.label_count(),
+            None => self.shape.direction_count(),
+        }
+    }
+
+    pub fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        match &self.graph {
+            Some(graph) => graph.opposite(direction),
+            None => self.shape.opposite(direction),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WfcConfigBuilder {
+    config: WfcConfig,
+    propagator_arrays: Option<(Vec<i32>, Vec<i32>, Vec<i32>)>,
+}
+
+impl WfcConfigBuilder {
+    pub fn new(width: usize, height: usize) -> Self {
+        let config = WfcConfig {
+            width,
+            height,
+            depth: 1,
+            weights: Vec::new(),
+            shape: GridShape::Square,
+            graph: None,
+            mask: None,
+            adjacency: Vec::new(),
+            boundary_x: BoundaryMode::Open,
+            boundary_y: BoundaryMode::Open,
+            boundary_z: BoundaryMode::Open,
+            edge_patterns: Vec::new(),
+            sides: Default::default(),
+            start_bias: 0.0,
+            start_x: 0.5,
+            start_y: 0.5,
+            start_z: 0.5,
+            selection: CellSelection::MinEntropy,
+            pattern_choice: PatternChoice::WeightedRandom,
+            temperature: 1.0,
+            backtracking: Backtracking::Trail,
+            max_snapshots: 0,
+            snapshot_interval_percent: 0.0,
+            snapshot_policy: SnapshotPolicy::Progress,
+            max_snapshot_bytes: 0,
+            snapshot_keyframe_interval: 16,
+            max_nogoods: 64,
+            seed: 0,
+        };
+
+        Self {
+            config,
+            propagator_arrays: None,
+        }
+    }
+
+    /// One weight per pattern. The number of weights defines the pattern count.
+    pub fn weights(mut self, weights: Vec<f64>) -> Self {
+        self.config.weights = weights;
+        self
+    }
+
+    /// The cell layout. Adjacency is given per direction of this shape.
+    pub fn shape(mut self, shape: GridShape) -> Self {
+        self.config.shape = shape;
+        self
+    }
+
+    /// Number of z layers. Only `GridShape::Voxel` supports more than one.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.config.depth = depth;
+        self
+    }
+
+    /// Replaces the patterns allowed in `direction` of `pattern`. Each of them
+    /// must allow `pattern` in the opposite direction as well, or `build` fails.
+    pub fn adjacency<D, I>(mut self, pattern: PatternIndex, direction: D, compatible: I) -> Self
+    where
+        D: GridDirection,
+        I: IntoIterator<Item = PatternIndex>,
+    {
+        let list = self.adjacency_list_mut(pattern, direction.into());
+
+        list.clear();
+        for p in compatible {
+            if !list.contains(&p) {
+                list.push(p);
+            }
+        }
+        self
+    }
+
+    /// Allows `b` in `direction` of `a`, and `a` in the opposite direction of `b`.
+    pub fn allow<D: GridDirection>(mut self, a: PatternIndex, direction: D, b: PatternIndex) -> Self {
+        let opposite = direction.opposite();
+
+        for &(from, dir, to) in &[(a, direction, b), (b, opposite, a)] {
+            let list = self.adjacency_list_mut(from, dir.into());
+
+            if !list.contains(&to) {
+                list.push(to);
+            }
+        }
+        self
+    }
+
+    /// The flat layout produced by the JS ruleset: the compatible patterns of
+    /// pattern `t` in direction `d` are `data[offsets[i]..offsets[i] + lengths[i]]`
+    /// where `i = d * t_count + t`, for each direction `d` of the shape. Replaces
+    /// any adjacency set so far.
+    pub fn propagator_arrays(mut self, data: Vec<i32>, offsets: Vec<i32>, lengths: Vec<i32>) -> Self {
+        self.propagator_arrays = Some((data, offsets, lengths));
+        self
+    }
+
+    /// Wraps every axis. Shorthand for `periodic_x`, `periodic_y` and `periodic_z`.
+    pub fn periodic(self, periodic: bool) -> Self {
+        self.periodic_x(periodic).periodic_y(periodic).periodic_z(periodic)
+    }
+
+    /// Wraps the west edge around to the east one, e.g. for cylinders.
+    pub fn periodic_x(self, periodic: bool) -> Self {
+        self.boundary_x(if periodic { BoundaryMode::Periodic } else { BoundaryMode::Open })
+    }
+
+    pub fn periodic_y(self, periodic: bool) -> Self {
+        self.boundary_y(if periodic { BoundaryMode::Periodic } else { BoundaryMode::Open })
+    }
+
+    /// Only matters for `GridShape::Voxel`.
+    pub fn periodic_z(self, periodic: bool) -> Self {
+        self.boundary_z(if periodic { BoundaryMode::Periodic } else { BoundaryMode::Open })
+    }
+
+    /// Behavior past the west and east edges.
+    pub fn boundary_x(mut self, mode: BoundaryMode) -> Self {
+        self.config.boundary_x = mode;
+        self
+    }
+
+    /// Behavior past the north and south edges.
+    pub fn boundary_y(mut self, mode: BoundaryMode) -> Self {
+        self.config.boundary_y = mode;
+        self
+    }
+
+    /// Behavior past the first and last z layers.
+    pub fn boundary_z(mut self, mode: BoundaryMode) -> Self {
+        self.config.boundary_z = mode;
+        self
+    }
+
+    /// The patterns allowed along edges in `BoundaryMode::EdgeCompatible`.
+    pub fn edge_patterns<I>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = PatternIndex>,
+    {
+        self.config.edge_patterns = patterns.into_iter().collect();
+        self
+    }
+
+    /// Requires patterns along the outermost row or column on `side`.
+    pub fn side(mut self, side: Direction, spec: SideSpec) -> Self {
+        self.config.sides[side as usize] = spec;
+        self
+    }
+
+    /// Limits generation to the cells where `active` is true, indexed like the
+    /// output. Masked-out cells are never observed and don't count toward the
+    /// total; their neighbors see them as described by `mode`.
+    pub fn mask(mut self, active: Vec<bool>, mode: MaskMode) -> Self {
+        self.config.mask = Some(Mask { active, mode });
+        self
+    }
+
+    /// Biases observation towards (`x`, `y`), given as fractions of the output size.
+    pub fn start_bias(mut self, bias: f64, x: f64, y: f64) -> Self {
+        self.config.start_bias = bias;
+        self.config.start_x = x;
+        self.config.start_y = y;
+        self
+    }
+
+    /// The z fraction for `start_bias` on a voxel grid. Defaults to the middle layer.
+    pub fn start_z(mut self, z: f64) -> Self {
+        self.config.start_z = z;
+        self
+    }
+
+    /// How the next cell to observe is picked. Defaults to `CellSelection::MinEntropy`.
+    pub fn selection(mut self, selection: CellSelection) -> Self {
+        self.config.selection = selection;
+        self
+    }
+
+    /// How an observed cell picks its pattern. Defaults to `PatternChoice::WeightedRandom`.
+    pub fn pattern_choice(mut self, choice: PatternChoice) -> Self {
+        self.config.pattern_choice = choice;
+        self
+    }
+
+    /// Only used by `PatternChoice::Temperature`. Defaults to 1, i.e. the plain weights.
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.config.temperature = temperature;
+        self
+    }
+
+    /// How a failed observation is undone. Defaults to `Backtracking::Trail`.
+    pub fn backtracking(mut self, backtracking: Backtracking) -> Self {
+        self.config.backtracking = backtracking;
+        self
+    }
+
+    /// How many observations can be undone. 0 turns backtracking off.
+    pub fn max_snapshots(mut self, max_snapshots: usize) -> Self {
+        self.config.max_snapshots = max_snapshots;
+        self
+    }
+
+    /// Minimum progress (0.0 - 1.0) between two snapshots. Only used by
+    /// `Backtracking::Snapshot`.
+    pub fn snapshot_interval_percent(mut self, interval: f64) -> Self {
+        self.config.snapshot_interval_percent = interval;
+        self
+    }
+
+    /// When snapshots are taken, and which are kept once `max_snapshots` or
+    /// `max_snapshot_bytes` is reached. Only used by `Backtracking::Snapshot`.
+    /// Defaults to `SnapshotPolicy::Progress`.
+    pub fn snapshot_policy(mut self, policy: SnapshotPolicy) -> Self {
+        self.config.snapshot_policy = policy;
+        self
+    }
+
+    /// Caps the total size of the snapshots kept by `Backtracking::Snapshot`.
+    /// 0, the default, leaves `max_snapshots` as the only limit. The newest
+    /// snapshot is kept even when it alone is larger.
+    pub fn max_snapshot_bytes(mut self, max_bytes: usize) -> Self {
+        self.config.max_snapshot_bytes = max_bytes;
+        self
+    }
+
+    /// Every `interval`th snapshot of `Backtracking::Snapshot` is stored in full,
+    /// and the ones between only as the words changed since the snapshot before.
+    /// Restoring one replays at most `interval - 1` of those. Defaults to 16;
+    /// 1 stores every snapshot in full.
+    pub fn snapshot_keyframe_interval(mut self, interval: usize) -> Self {
+        self.config.snapshot_keyframe_interval = interval;
+        self
+    }
+
+    /// Most nogoods learned from contradictions per attempt. Not used by
+    /// `Backtracking::Snapshot`; 0 turns learning off.
+    pub fn max_nogoods(mut self, max_nogoods: usize) -> Self {
+        self.config.max_nogoods = max_nogoods;
+        self
+    }
+
+    /// Seed for the solver's internal PRNG. The same seed and config always
+    /// produce the same output.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    /// Checks the configuration. Besides the ranges and counts, every allowed
+    /// pair must be allowed both ways: `allow` takes care of that, while lists
+    /// set with `adjacency` or `propagator_arrays` fail with
+    /// `WfcError::AsymmetricAdjacency` when they only give one side.
+    pub fn build(self) -> Result<WfcConfig, WfcError> {
+        let mut config = self.config;
+        let t_count = config.weights.len();
+        let d_count = config.direction_count();
+
+        if config.width == 0 || config.height == 0 {
+            return Err(WfcError::InvalidDimensions {
+                width: config.width,
+                height: config.height,
+            });
+        }
+
+        let flat = config.shape != GridShape::Voxel;
+        if config.depth == 0 || (flat && config.depth > 1) {
+            return Err(WfcError::InvalidDepth { depth: config.depth });
+        }
+
+        let n_cells = config.width.checked_mul(config.height).and_then(|n| n.checked_mul(config.depth));
+        if n_cells.is_none() {
+            return Err(WfcError::InvalidDimensions {
+                width: config.width,
+                height: config.height,
+            });
+        }
+
+        if let Some(mask) = &config.mask {
+            mask.check(config.width, config.height, config.depth)?;
+        }
+
+        if t_count == 0 {
+            return Err(WfcError::NoPatterns);
+        }
+
+        for (t, &weight) in config.weights.iter().enumerate() {
+            if !weight.is_finite() || weight < 0.0 {
+                let pattern = PatternIndex { base: t };
+
+                return Err(WfcError::InvalidWeight { pattern, weight });
+            }
+        }
+
+        if config.weights.iter().all(|&weight| weight == 0.0) {
+            return Err(WfcError::ZeroWeights);
+        }
+
+        let temperature = config.temperature;
+        if !temperature.is_finite() || temperature <= 0.0 {
+            return Err(WfcError::InvalidTemperature { temperature });
+        }
+
+        if !config.start_bias.is_finite() {
+            return Err(WfcError::InvalidStartBias { bias: config.start_bias });
+        }
+
+        let interval = config.snapshot_interval_percent;
+        if !interval.is_finite() {
+            return Err(WfcError::InvalidSnapshotInterval { interval });
+        }
+
+        let periodic_y = config.boundary_y == BoundaryMode::Periodic;
+        if config.shape == GridShape::Hex && periodic_y && config.height % 2 == 1 {
+            return Err(WfcError::InvalidDimensions {
+                width: config.width,
+                height: config.height,
+            });
+        }
+
+        if let Some((data, offsets, lengths)) = self.propagator_arrays {
+            config.adjacency = adjacency_from_arrays(t_count, d_count, &data, &offsets, &lengths)?;
+        }
+
+        if config.adjacency.len() > t_count {
+            return Err(WfcError::PatternCountMismatch {
+                expected: t_count,
+                actual: config.adjacency.len(),
+            });
+        }
+
+        config.adjacency.resize(t_count, Vec::new());
+        for lists in &mut config.adjacency {
+            if lists.len() > d_count {
+                let direction = DirectionIndex { base: lists.len() - 1 };
+
+                return Err(WfcError::DirectionOutOfRange { direction, d_count });
+            }
+            lists.resize(d_count, Vec::new());
+        }
+
+        let fixed_patterns = [config.boundary_x, config.boundary_y, config.boundary_z]
+            .iter()
+            .filter_map(|mode| match *mode {
+                BoundaryMode::Fixed(pattern) => Some(pattern),
+                _ => None,
+            })
+            .chain(config.mask.iter().filter_map(|mask| match mask.mode {
+                MaskMode::Void(pattern) => Some(pattern),
+                MaskMode::Open => None,
+            }))
+            .collect::<Vec<_>>();
+
+        let referenced = config
+            .adjacency
+            .iter()
+            .flatten()
+            .flatten()
+            .chain(&config.edge_patterns)
+            .chain(&fixed_patterns)
+            .chain(config.sides.iter().flat_map(SideSpec::patterns));
+
+        for &pattern in referenced {
+            if pattern.base >= t_count {
+                return Err(WfcError::PatternOutOfRange { pattern, t_count });
+            }
+        }
+
+        check_symmetric(&config)?;
+
+        Ok(config)
+    }
+
+    fn adjacency_list_mut(&mut self, pattern: PatternIndex, direction: DirectionIndex) -> &mut Vec<PatternIndex> {
+        let adjacency = &mut self.config.adjacency;
+
+        if adjacency.len() <= pattern.base {
+            adjacency.resize(pattern.base + 1, Vec::new());
+        }
+
+        let lists = &mut adjacency[pattern.base];
+        if lists.len() <= direction.base {
+            lists.resize(direction.base + 1, Vec::new());
+        }
+
+        &mut lists[direction.base]
+    }
+}
+
+fn adjacency_from_arrays(
+    t_count: usize,
+    d_count: usize,
+    data: &[i32],
+    offsets: &[i32],
+    lengths: &[i32],
+) -> Result<Vec<Vec<Vec<PatternIndex>>>, WfcError> {
+    let expected = d_count * t_count;
+
+    for actual in [offsets.len(), lengths.len()] {
+        if actual != expected {
+            return Err(WfcError::PatternCountMismatch { expected, actual });
+        }
+    }
+
+    let mut adjacency = vec![vec![Vec::new(); d_count]; t_count];
+
+    for d in 0..d_count {
+        for (t, lists) in adjacency.iter_mut().enumerate() {
+            let lookup_idx = d * t_count + t;
+            let start = offsets[lookup_idx];
+            let len = lengths[lookup_idx];
+            let in_range = start >= 0 && len >= 0 && (start as usize) + (len as usize) <= data.len();
+
+            if !in_range {
+                return Err(WfcError::InvalidPropagatorRange { lookup_idx, start, len });
+            }
+
+            let start = start as usize;
+            let end = start + len as usize;
+
+            lists[d] = data[start..end]
+                .iter()
+                .map(|&id| PatternIndex { base: id as usize })
+                .collect();
+        }
+    }
+
+    Ok(adjacency)
+}
+
+// Propagation counts each pattern's supports from the neighbor's side, so every
+// allowed pair must also be allowed the other way around.
+fn check_symmetric(config: &WfcConfig) -> Result<(), WfcError> {
+    let sorted: Vec<Vec<Vec<PatternIndex>>> = config
+        .adjacency
+        .iter()
+        .map(|lists| {
+            lists
+                .iter()
+                .map(|list| {
+                    let mut list = list.clone();
+                    list.sort_unstable_by_key(|p| p.base);
+                    list
+                })
+                .collect()
+        })
+        .collect();
+
+    for (t, lists) in sorted.iter().enumerate() {
+        for (d, list) in lists.iter().enumerate() {
+            let direction = DirectionIndex { base: d };
+            let opposite = config.opposite(direction);
+
+            for &neighbor in list {
+                let back = &sorted[neighbor.base][opposite.base];
+
+                if back.binary_search_by_key(&t, |p| p.base).is_err() {
+                    let pattern = PatternIndex { base: t };
+
+                    return Err(WfcError::AsymmetricAdjacency { pattern, direction, neighbor });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use std::collections::HashSet;
+
+// User-placed bans that must hold for the whole generation. They are
+// re-applied whenever the wave is rebuilt by `clear()` or `revert()`.
+#[derive(Clone, Default)]
+pub struct Constraints {
+    bans: Vec<(CellIndex, PatternIndex)>,
+
+    // The (cell, pattern) pairs in `bans`, so that repeating a ban adds nothing.
+    present: HashSet<(usize, usize)>,
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the ban unless it is already there.
+    pub fn push(&mut self, cell: CellIndex, pattern: PatternIndex) {
+        if self.present.insert((cell.base, pattern.base)) {
+            self.bans.push((cell, pattern));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bans.is_empty()
+    }
+
+    /// Drops every ban added after the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        for (cell, pattern) in self.bans.drain(len.min(self.bans.len())..) {
+            self.present.remove(&(cell.base, pattern.base));
+        }
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&(CellIndex, PatternIndex)) -> bool,
+    {
+        let present = &mut self.present;
+
+        self.bans.retain(|ban| {
+            let keep = f(ban);
+            if !keep {
+                present.remove(&(ban.0.base, ban.1.base));
+            }
+            keep
+        });
+    }
+
+    /// Adds every ban of `other` that is not already there.
+    pub fn extend(&mut self, other: &Constraints) {
+        for &(cell, pattern) in &other.bans {
+            self.push(cell, pattern);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bans.clear();
+        self.present.clear();
+    }
+
+    pub fn bans(&self) -> &[(CellIndex, PatternIndex)] {
+        &self.bans
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bans.capacity() * std::mem::size_of::<(CellIndex, PatternIndex)>()
+            + self.present.capacity() * std::mem::size_of::<(usize, usize)>()
+    }
+}
@@ -1,4 +1,8 @@
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Direction {
     West = 0,
     South = 1,
@@ -14,8 +18,6 @@ pub const DIRECTIONS: [Direction; 4] = [
 ];
 
 pub struct DirectionInfo {
-    pub dx: i32,
-    pub dy: i32,
     pub opposite: Direction,
 }
 
@@ -23,25 +25,46 @@ impl Direction {
     pub fn info(self) -> DirectionInfo {
         match self {
             Direction::West => DirectionInfo {
-                dx: -1,
-                dy: 0,
                 opposite: Direction::East,
             },
             Direction::South => DirectionInfo {
-                dx: 0,
-                dy: 1,
                 opposite: Direction::North,
             },
             Direction::East => DirectionInfo {
-                dx: 1,
-                dy: 0,
                 opposite: Direction::West,
             },
             Direction::North => DirectionInfo {
-                dx: 0,
-                dy: -1,
                 opposite: Direction::South,
             },
         }
     }
 }
+
+/// A direction in any `Topology`, numbered `0..direction_count()`. On the
+/// square grid it matches the [`Direction`] discriminant.
+///
+/// [`Direction`]: crate::Direction
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct DirectionIndex {
+    pub base: usize,
+}
+
+impl From<Direction> for DirectionIndex {
+    #[inline(always)]
+    fn from(direction: Direction) -> Self {
+        Self {
+            base: direction as usize,
+        }
+    }
+}
+
+/// The named directions of a built-in grid shape, as accepted by the config builder.
+pub trait GridDirection: Copy + Into<DirectionIndex> {
+    fn opposite(self) -> Self;
+}
+
+impl GridDirection for Direction {
+    fn opposite(self) -> Self {
+        self.info().opposite
+    }
+}
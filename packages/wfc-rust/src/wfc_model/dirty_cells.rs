@@ -31,18 +31,22 @@ impl DirtyCells {
     pub fn flush_to_js(&mut self) -> Vec<i32> {
         let raw_list = std::mem::take(&mut self.list);
 
-        let result = raw_list
+        raw_list
             .into_iter()
             .map(|idx| {
                 self.is_dirty[idx] = false;
 
-                let val = idx.base as i32;
-
-                val
+                idx.base as i32
             })
-            .collect();
+            .collect()
+    }
 
-        result
+    /// Empties the list, passing each cell to `f`.
+    pub fn drain<F: FnMut(CellIndex)>(&mut self, mut f: F) {
+        for idx in self.list.drain(..) {
+            self.is_dirty[idx] = false;
+            f(idx);
+        }
     }
 
     pub fn clear(&mut self) {
@@ -53,6 +57,12 @@ impl DirtyCells {
         flags.fill(false);
     }
 
+    pub fn memory_usage_bytes(&self) -> usize {
+        let list_bytes = self.list.capacity() * std::mem::size_of::<CellIndex>();
+
+        list_bytes + self.is_dirty.memory_usage_bytes()
+    }
+
     pub fn mark_all_dirty(&mut self) {
         let n_cells = self.is_dirty.len();
 
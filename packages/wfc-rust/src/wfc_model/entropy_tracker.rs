@@ -3,6 +3,14 @@ use crate::wfc_model::cell_collection::CellCollection;
 use crate::wfc_model::pattern_collection::PatternIndex;
 use crate::wfc_model::pattern_collection::PatternCollection;
 
+/// The running sums of one cell, as recorded on the backtracking trail.
+#[derive(Clone, Copy)]
+pub struct CellWeights {
+    sum: f64,
+    log_sum: f64,
+    entropy: f64,
+}
+
 #[derive(Clone)]
 pub struct EntropyTracker {
 
@@ -98,6 +106,23 @@ impl EntropyTracker {
         }
     }
 
+    #[inline(always)]
+    pub fn cell_weights(&self, cell: CellIndex) -> CellWeights {
+        CellWeights {
+            sum: self.weights[cell],
+            log_sum: self.log_weights[cell],
+            entropy: self.entropies[cell],
+        }
+    }
+
+    /// Undoes a `ban_pattern`, putting back the sums the cell had before it.
+    pub fn unban_pattern(&mut self, cell: CellIndex, previous: CellWeights) {
+        self.possible_pattern_count[cell] += 1;
+        self.weights[cell] = previous.sum;
+        self.log_weights[cell] = previous.log_sum;
+        self.entropies[cell] = previous.entropy;
+    }
+
     #[inline(always)]
     pub fn pattern_determined(&self, cell: CellIndex) -> bool {
         let count = self.possible_pattern_count[cell];
@@ -141,4 +166,15 @@ impl EntropyTracker {
     pub fn entropies_ptr(&self) -> *const f64 {
         self.entropies.as_ptr()
     }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        let per_cell = self.possible_pattern_count.memory_usage_bytes()
+            + self.weights.memory_usage_bytes()
+            + self.log_weights.memory_usage_bytes()
+            + self.entropies.memory_usage_bytes();
+        let per_pattern = (self.pattern_weights.data.capacity() + self.pattern_log_weights.data.capacity())
+            * std::mem::size_of::<f64>();
+
+        per_cell + per_pattern
+    }
 }
@@ -0,0 +1,141 @@
+use crate::wfc_model::direction::DirectionIndex;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::restart::RestartPolicy;
+use std::fmt;
+
+/// Why a configuration, constraint or edit was rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WfcError {
+    /// The output grid must have at least one cell on each axis, fit in memory,
+    /// and have an even height to wrap a hex grid vertically.
+    InvalidDimensions { width: usize, height: usize },
+
+    /// Only voxel grids have more than one layer, and every grid has at least one.
+    /// Also returned by edits that only work on a single layer.
+    InvalidDepth { depth: usize },
+
+    /// A cell mask must have one entry per cell of the output.
+    InvalidMask { expected: usize, actual: usize },
+
+    /// A cell mask that leaves no cell to generate.
+    EmptyMask,
+
+    /// A ruleset needs at least one pattern to place.
+    NoPatterns,
+
+    /// Two inputs disagree on how many patterns (or propagator entries) exist.
+    PatternCountMismatch { expected: usize, actual: usize },
+
+    /// Weights feed ln() in the entropy tracker, so they must be finite and non-negative.
+    InvalidWeight { pattern: PatternIndex, weight: f64 },
+
+    /// At least one pattern needs a positive weight, or no cell could pick any.
+    ZeroWeights,
+
+    /// Pattern weights are raised to 1 / temperature, so it must be finite and positive.
+    InvalidTemperature { temperature: f64 },
+
+    /// The start bias scales distances into observation scores, so it must be finite.
+    InvalidStartBias { bias: f64 },
+
+    /// Progress is compared against the snapshot interval, so it must be finite.
+    InvalidSnapshotInterval { interval: f64 },
+
+    /// A pattern id outside 0..t_count, in an adjacency list or a cell constraint.
+    PatternOutOfRange { pattern: PatternIndex, t_count: usize },
+
+    /// Adjacency given for a direction the grid shape does not have.
+    DirectionOutOfRange { direction: DirectionIndex, d_count: usize },
+
+    /// A graph edge label that does not exist, or whose opposite does not lead back to it.
+    InvalidEdgeLabel { label: usize, label_count: usize },
+
+    /// A graph edge to a missing node, or through a port that is already connected.
+    InvalidGraphEdge { node: usize, label: usize },
+
+    /// `neighbor` is allowed in `direction` of `pattern`, but `pattern` is not
+    /// allowed in the opposite direction of `neighbor`. Propagation counts each
+    /// pattern's supports from the neighbor's side, so rule sets that only list
+    /// one side of a pair are rejected.
+    AsymmetricAdjacency {
+        pattern: PatternIndex,
+        direction: DirectionIndex,
+        neighbor: PatternIndex,
+    },
+
+    /// A flat propagator offset/length pair points outside of the data array.
+    InvalidPropagatorRange { lookup_idx: usize, start: i32, len: i32 },
+
+    /// A geometric restart policy whose budget starts at zero, shrinks or is not a number.
+    InvalidRestartPolicy { policy: RestartPolicy },
+
+    /// Coordinates outside of the output grid.
+    CellOutOfRange { x: usize, y: usize },
+
+    /// A layer past the depth of the output grid.
+    LayerOutOfRange { z: usize, depth: usize },
+
+    /// Applying a constraint at this cell left some cell without any pattern.
+    Contradiction { x: usize, y: usize },
+}
+
+impl fmt::Display for WfcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WfcError::InvalidDimensions { width, height } => {
+                write!(f, "invalid output dimensions {}x{}", width, height)
+            }
+            WfcError::InvalidDepth { depth } => write!(f, "invalid depth {} for this grid shape", depth),
+            WfcError::InvalidMask { expected, actual } => {
+                write!(f, "mask has {} entries, expected {}", actual, expected)
+            }
+            WfcError::EmptyMask => write!(f, "mask leaves no active cell"),
+            WfcError::NoPatterns => write!(f, "ruleset has no patterns"),
+            WfcError::PatternCountMismatch { expected, actual } => {
+                write!(f, "expected {} entries, got {}", expected, actual)
+            }
+            WfcError::InvalidWeight { pattern, weight } => {
+                write!(f, "pattern {} has invalid weight {}", pattern.base, weight)
+            }
+            WfcError::ZeroWeights => write!(f, "every pattern has a weight of zero"),
+            WfcError::InvalidTemperature { temperature } => write!(f, "invalid temperature {}", temperature),
+            WfcError::InvalidStartBias { bias } => write!(f, "invalid start bias {}", bias),
+            WfcError::InvalidSnapshotInterval { interval } => write!(f, "invalid snapshot interval {}", interval),
+            WfcError::PatternOutOfRange { pattern, t_count } => {
+                write!(f, "pattern {} is out of range for {} patterns", pattern.base, t_count)
+            }
+            WfcError::DirectionOutOfRange { direction, d_count } => {
+                write!(f, "direction {} is out of range for {} directions", direction.base, d_count)
+            }
+            WfcError::InvalidEdgeLabel { label, label_count } => {
+                write!(f, "edge label {} is invalid for {} labels", label, label_count)
+            }
+            WfcError::InvalidGraphEdge { node, label } => {
+                write!(f, "cannot connect node {} through label {}", node, label)
+            }
+            WfcError::AsymmetricAdjacency { pattern, direction, neighbor } => write!(
+                f,
+                "pattern {} allows {} in direction {}, but not the other way around",
+                pattern.base, neighbor.base, direction.base
+            ),
+            WfcError::InvalidPropagatorRange { lookup_idx, start, len } => write!(
+                f,
+                "propagator entry {} has invalid range start={} len={}",
+                lookup_idx, start, len
+            ),
+            WfcError::InvalidRestartPolicy { policy } => write!(f, "invalid restart policy {:?}", policy),
+            WfcError::CellOutOfRange { x, y } => write!(f, "cell ({}, {}) is outside of the output", x, y),
+            WfcError::LayerOutOfRange { z, depth } => write!(f, "layer {} is outside of {} layers", z, depth),
+            WfcError::Contradiction { x, y } => write!(f, "constraint at ({}, {}) causes a contradiction", x, y),
+        }
+    }
+}
+
+impl std::error::Error for WfcError {}
+
+#[cfg(feature = "wasm")]
+impl From<WfcError> for wasm_bindgen::JsValue {
+    fn from(err: WfcError) -> Self {
+        wasm_bindgen::JsError::new(&err.to_string()).into()
+    }
+}
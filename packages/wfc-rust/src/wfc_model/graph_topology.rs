@@ -0,0 +1,132 @@
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::direction::{DirectionIndex, GridDirection};
+use crate::wfc_model::error::WfcError;
+use crate::wfc_model::topology::Topology;
+
+// Marks an unconnected port in `GraphTopology::neighbors`.
+const NO_NEIGHBOR: usize = usize::MAX;
+
+/// A caller-supplied graph, e.g. Voronoi regions, a quad mesh or a room graph.
+/// Nodes are cells. Each edge leaves a node through a labeled port and enters
+/// its neighbor through the opposite label, so a port holds at most one edge.
+#[derive(Clone, Debug)]
+pub struct GraphTopology {
+    node_count: usize,
+
+    // Indexed by label.
+    opposites: Vec<usize>,
+
+    // Indexed as [node * label_count + label].
+    neighbors: Vec<usize>,
+}
+
+/// An edge label of a [`GraphTopology`], used to give adjacency per label.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct EdgeLabel {
+    label: usize,
+    opposite: usize,
+}
+
+impl GridDirection for EdgeLabel {
+    fn opposite(self) -> Self {
+        Self {
+            label: self.opposite,
+            opposite: self.label,
+        }
+    }
+}
+
+impl From<EdgeLabel> for DirectionIndex {
+    #[inline(always)]
+    fn from(label: EdgeLabel) -> Self {
+        Self { base: label.label }
+    }
+}
+
+impl GraphTopology {
+    /// `opposites[l]` is the label an edge arrives with when it leaves through
+    /// label `l`. It must map every label back to itself in two steps.
+    pub fn new(node_count: usize, opposites: Vec<usize>) -> Result<Self, WfcError> {
+        let label_count = opposites.len();
+
+        for (label, &opposite) in opposites.iter().enumerate() {
+            if opposite >= label_count || opposites[opposite] != label {
+                return Err(WfcError::InvalidEdgeLabel { label, label_count });
+            }
+        }
+
+        Ok(Self {
+            node_count,
+            opposites,
+            neighbors: vec![NO_NEIGHBOR; node_count * label_count],
+        })
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn label_count(&self) -> usize {
+        self.opposites.len()
+    }
+
+    /// Panics if `label` is out of range.
+    pub fn label(&self, label: usize) -> EdgeLabel {
+        EdgeLabel {
+            label,
+            opposite: self.opposites[label],
+        }
+    }
+
+    /// Connects `a` through `label` to `b`, and `b` through the opposite label to `a`.
+    pub fn connect(&mut self, a: usize, label: usize, b: usize) -> Result<(), WfcError> {
+        let label_count = self.label_count();
+        if label >= label_count {
+            return Err(WfcError::InvalidEdgeLabel { label, label_count });
+        }
+
+        let opposite = self.opposites[label];
+        let ports = [(a, label, b), (b, opposite, a)];
+
+        for &(node, port, _) in &ports {
+            let taken = node < self.node_count && self.neighbors[node * label_count + port] != NO_NEIGHBOR;
+
+            if node >= self.node_count || taken {
+                return Err(WfcError::InvalidGraphEdge { node, label: port });
+            }
+        }
+
+        for &(node, port, other) in &ports {
+            self.neighbors[node * label_count + port] = other;
+        }
+        Ok(())
+    }
+}
+
+impl Topology for GraphTopology {
+    fn cell_count(&self) -> usize {
+        self.node_count
+    }
+
+    fn direction_count(&self) -> usize {
+        self.opposites.len()
+    }
+
+    #[inline(always)]
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex> {
+        let neighbor = self.neighbors[cell.base * self.opposites.len() + direction.base];
+
+        if neighbor == NO_NEIGHBOR {
+            None
+        } else {
+            Some(CellIndex { base: neighbor })
+        }
+    }
+
+    #[inline(always)]
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        DirectionIndex {
+            base: self.opposites[direction.base],
+        }
+    }
+}
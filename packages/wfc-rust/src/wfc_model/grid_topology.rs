@@ -0,0 +1,119 @@
+use crate::wfc_model::cell::{Cell, CellIndex};
+use crate::wfc_model::direction::{DirectionIndex, GridDirection, DIRECTIONS};
+use crate::wfc_model::topology::{wrap_axis, Axis, Topology};
+
+/// The eight neighbors of a square cell (the Moore neighborhood). The first
+/// four match [`Direction`], followed by the diagonals. Opposite directions are
+/// two apart within each group of four.
+///
+/// [`Direction`]: crate::Direction
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MooreDirection {
+    West = 0,
+    South = 1,
+    East = 2,
+    North = 3,
+    SouthWest = 4,
+    SouthEast = 5,
+    NorthEast = 6,
+    NorthWest = 7,
+}
+
+pub const MOORE_DIRECTIONS: [MooreDirection; 8] = [
+    MooreDirection::West,
+    MooreDirection::South,
+    MooreDirection::East,
+    MooreDirection::North,
+    MooreDirection::SouthWest,
+    MooreDirection::SouthEast,
+    MooreDirection::NorthEast,
+    MooreDirection::NorthWest,
+];
+
+// (dx, dy) indexed by `MooreDirection`, so the first four also serve `Direction`.
+const OFFSETS: [(i32, i32); 8] = [(-1, 0), (0, 1), (1, 0), (0, -1), (-1, 1), (1, 1), (1, -1), (-1, -1)];
+
+#[inline(always)]
+fn opposite_index(d: usize) -> usize {
+    (d & !3) + (d + 2) % 4
+}
+
+impl GridDirection for MooreDirection {
+    fn opposite(self) -> Self {
+        MOORE_DIRECTIONS[opposite_index(self as usize)]
+    }
+}
+
+impl From<MooreDirection> for DirectionIndex {
+    #[inline(always)]
+    fn from(direction: MooreDirection) -> Self {
+        Self {
+            base: direction as usize,
+        }
+    }
+}
+
+/// The square grid with 4 neighbors, or 8 with `diagonals`, optionally
+/// wrapping on either axis.
+pub struct GridTopology {
+    width: usize,
+    height: usize,
+    periodic_x: bool,
+    periodic_y: bool,
+    diagonals: bool,
+    cell: Cell,
+}
+
+impl GridTopology {
+    pub fn new(width: usize, height: usize, periodic_x: bool, periodic_y: bool, diagonals: bool) -> Self {
+        Self {
+            width,
+            height,
+            periodic_x,
+            periodic_y,
+            diagonals,
+            cell: Cell::new(width, height),
+        }
+    }
+
+    // A diagonal step off a corner leaves along y first, like any other step.
+    #[inline(always)]
+    fn step(&self, cell: CellIndex, direction: DirectionIndex) -> Result<CellIndex, Axis> {
+        let (x, y) = self.cell.get_coords(cell);
+        let (dx, dy) = OFFSETS[direction.base];
+        let ny = wrap_axis(y + dy, self.height, self.periodic_y).ok_or(Axis::Y)?;
+        let nx = wrap_axis(x + dx, self.width, self.periodic_x).ok_or(Axis::X)?;
+
+        Ok(self.cell.get_index(nx, ny))
+    }
+}
+
+impl Topology for GridTopology {
+    fn cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn direction_count(&self) -> usize {
+        if self.diagonals {
+            MOORE_DIRECTIONS.len()
+        } else {
+            DIRECTIONS.len()
+        }
+    }
+
+    #[inline(always)]
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex> {
+        self.step(cell, direction).ok()
+    }
+
+    #[inline(always)]
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        DirectionIndex {
+            base: opposite_index(direction.base),
+        }
+    }
+
+    fn exit_axis(&self, cell: CellIndex, direction: DirectionIndex) -> Option<Axis> {
+        self.step(cell, direction).err()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::wfc_model::cell::{Cell, CellIndex};
+use crate::wfc_model::direction::{DirectionIndex, GridDirection};
+use crate::wfc_model::topology::{wrap_axis, Axis, Topology};
+
+/// The six neighbors of a pointy-top hexagon, counter-clockwise from east.
+/// Opposite directions are three apart.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HexDirection {
+    East = 0,
+    NorthEast = 1,
+    NorthWest = 2,
+    West = 3,
+    SouthWest = 4,
+    SouthEast = 5,
+}
+
+pub const HEX_DIRECTIONS: [HexDirection; 6] = [
+    HexDirection::East,
+    HexDirection::NorthEast,
+    HexDirection::NorthWest,
+    HexDirection::West,
+    HexDirection::SouthWest,
+    HexDirection::SouthEast,
+];
+
+impl GridDirection for HexDirection {
+    fn opposite(self) -> Self {
+        HEX_DIRECTIONS[(self as usize + 3) % 6]
+    }
+}
+
+impl From<HexDirection> for DirectionIndex {
+    #[inline(always)]
+    fn from(direction: HexDirection) -> Self {
+        Self {
+            base: direction as usize,
+        }
+    }
+}
+
+// Odd rows are shifted half a cell to the east ("odd-r"), so the (dx, dy) of a
+// direction depends on the row parity. Indexed by `HexDirection`.
+const EVEN_ROW_OFFSETS: [(i32, i32); 6] = [(1, 0), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1)];
+const ODD_ROW_OFFSETS: [(i32, i32); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (0, 1), (1, 1)];
+
+/// Hexagons stored row-major as odd-r offset coordinates. Wrapping vertically
+/// needs an even height to keep the row parity intact.
+pub struct HexTopology {
+    width: usize,
+    height: usize,
+    periodic_x: bool,
+    periodic_y: bool,
+    cell: Cell,
+}
+
+impl HexTopology {
+    pub fn new(width: usize, height: usize, periodic_x: bool, periodic_y: bool) -> Self {
+        Self {
+            width,
+            height,
+            periodic_x,
+            periodic_y,
+            cell: Cell::new(width, height),
+        }
+    }
+
+    #[inline(always)]
+    fn step(&self, cell: CellIndex, direction: DirectionIndex) -> Result<CellIndex, Axis> {
+        let (x, y) = self.cell.get_coords(cell);
+        let offsets = if y & 1 == 0 { &EVEN_ROW_OFFSETS } else { &ODD_ROW_OFFSETS };
+        let (dx, dy) = offsets[direction.base];
+        let ny = wrap_axis(y + dy, self.height, self.periodic_y).ok_or(Axis::Y)?;
+        let nx = wrap_axis(x + dx, self.width, self.periodic_x).ok_or(Axis::X)?;
+
+        Ok(self.cell.get_index(nx, ny))
+    }
+}
+
+impl Topology for HexTopology {
+    fn cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn direction_count(&self) -> usize {
+        HEX_DIRECTIONS.len()
+    }
+
+    #[inline(always)]
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex> {
+        self.step(cell, direction).ok()
+    }
+
+    #[inline(always)]
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        DirectionIndex {
+            base: (direction.base + 3) % 6,
+        }
+    }
+
+    fn exit_axis(&self, cell: CellIndex, direction: DirectionIndex) -> Option<Axis> {
+        self.step(cell, direction).err()
+    }
+}
+
+/// Converts odd-r offset coordinates (the `x`, `y` of the output) to axial `(q, r)`.
+pub fn offset_to_axial(x: i32, y: i32) -> (i32, i32) {
+    let q = x - (y - (y & 1)) / 2;
+
+    (q, y)
+}
+
+/// Converts axial `(q, r)` coordinates to the odd-r offset `(x, y)` of the output.
+pub fn axial_to_offset(q: i32, r: i32) -> (i32, i32) {
+    let x = q + (r - (r & 1)) / 2;
+
+    (x, r)
+}
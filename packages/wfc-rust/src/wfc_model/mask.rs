@@ -0,0 +1,168 @@
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::constraints::Constraints;
+use crate::wfc_model::direction::DirectionIndex;
+use crate::wfc_model::error::WfcError;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::propagator::Propagator;
+use crate::wfc_model::topology::{Axis, Topology};
+
+/// What masked-out cells look like to the active cells next to them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskMode {
+    /// Like an open edge: no constraint.
+    Open,
+
+    /// As if the masked cell held this pattern.
+    Void(PatternIndex),
+}
+
+#[derive(Clone, Debug)]
+pub struct Mask {
+    // Indexed by cell. Inactive cells are left out of generation entirely.
+    pub active: Vec<bool>,
+    pub mode: MaskMode,
+}
+
+impl Mask {
+    #[inline(always)]
+    pub fn is_active(&self, cell: CellIndex) -> bool {
+        self.active[cell.base]
+    }
+
+    /// Checks that the mask covers a `width` x `height` x `depth` grid and
+    /// leaves at least one cell active.
+    pub fn check(&self, width: usize, height: usize, depth: usize) -> Result<(), WfcError> {
+        let expected = width * height * depth;
+
+        if self.active.len() != expected {
+            return Err(WfcError::InvalidMask {
+                expected,
+                actual: self.active.len(),
+            });
+        }
+
+        if !self.active.contains(&true) {
+            return Err(WfcError::EmptyMask);
+        }
+
+        Ok(())
+    }
+}
+
+/// The cells taking part in generation: all of them without a mask.
+pub fn active_cells(mask: Option<&Mask>, n_cells: usize) -> Vec<CellIndex> {
+    (0..n_cells)
+        .map(|i| CellIndex { base: i })
+        .filter(|&cell| match mask {
+            Some(mask) => mask.is_active(cell),
+            None => true,
+        })
+        .collect()
+}
+
+/// Wraps `topology` in a [`MaskedTopology`] when there is a mask, returning
+/// it along with the void bans of the mask.
+pub fn apply_mask(
+    topology: Box<dyn Topology>,
+    mask: Option<&Mask>,
+    propagator: &Propagator,
+    t_count: usize,
+) -> (Box<dyn Topology>, Constraints) {
+    match mask {
+        Some(mask) => {
+            let masked = MaskedTopology::new(topology, mask);
+            let void_bans = masked.void_bans(mask.mode, propagator, t_count);
+
+            (Box::new(masked), void_bans)
+        }
+        None => (topology, Constraints::new()),
+    }
+}
+
+/// Hides the inactive cells of a mask from another topology: they have no
+/// neighbors, and nothing has them as a neighbor.
+pub struct MaskedTopology {
+    inner: Box<dyn Topology>,
+    active: Vec<bool>,
+}
+
+impl MaskedTopology {
+    pub fn new(inner: Box<dyn Topology>, mask: &Mask) -> Self {
+        Self {
+            inner,
+            active: mask.active.clone(),
+        }
+    }
+
+    /// With `MaskMode::Void`, bans the patterns of active cells that cannot sit
+    /// next to the void pattern in the direction of a masked neighbor.
+    pub fn void_bans(&self, mode: MaskMode, propagator: &Propagator, t_count: usize) -> Constraints {
+        let mut bans = Constraints::new();
+        let void = match mode {
+            MaskMode::Open => return bans,
+            MaskMode::Void(void) => void,
+        };
+
+        for i in 0..self.inner.cell_count() {
+            let cell_idx = CellIndex { base: i };
+            if !self.active[i] {
+                continue;
+            }
+
+            for d_idx in 0..self.inner.direction_count() {
+                let d = DirectionIndex { base: d_idx };
+                let masked = match self.inner.neighbor(cell_idx, d) {
+                    Some(neighbor) => !self.active[neighbor.base],
+                    None => false,
+                };
+                if !masked {
+                    continue;
+                }
+
+                for t in 0..t_count {
+                    let pattern_idx = PatternIndex { base: t };
+
+                    if !propagator.is_compatible(pattern_idx, d, void) {
+                        bans.push(cell_idx, pattern_idx);
+                    }
+                }
+            }
+        }
+
+        bans
+    }
+}
+
+impl Topology for MaskedTopology {
+    fn cell_count(&self) -> usize {
+        self.inner.cell_count()
+    }
+
+    fn direction_count(&self) -> usize {
+        self.inner.direction_count()
+    }
+
+    #[inline(always)]
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex> {
+        if !self.active[cell.base] {
+            return None;
+        }
+
+        self.inner
+            .neighbor(cell, direction)
+            .filter(|neighbor| self.active[neighbor.base])
+    }
+
+    #[inline(always)]
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        self.inner.opposite(direction)
+    }
+
+    fn exit_axis(&self, cell: CellIndex, direction: DirectionIndex) -> Option<Axis> {
+        if !self.active[cell.base] {
+            return None;
+        }
+
+        self.inner.exit_axis(cell, direction)
+    }
+}
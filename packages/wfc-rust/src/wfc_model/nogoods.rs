@@ -0,0 +1,202 @@
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::cell_collection::CellCollection;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::trail::{Reason, Trail};
+use crate::wfc_model::wave::Wave;
+
+/// What a learned nogood says about the current wave.
+pub enum NogoodState {
+    /// At least one cell can no longer take its pattern, or several are still open.
+    Free,
+
+    /// Every cell but this one is set, so its pattern must go.
+    Unit(CellIndex, PatternIndex),
+
+    /// Every cell is set: the contradiction is back.
+    Violated,
+}
+
+/// Sets of (cell, pattern) assignments that led to a contradiction and must
+/// not all hold again. Learned from the trail, so `Backtracking::Snapshot`
+/// never fills them. They are forgotten when generation starts over.
+#[derive(Clone)]
+pub struct Nogoods {
+    nogoods: Vec<Vec<(CellIndex, PatternIndex)>>,
+    max_nogoods: usize,
+
+    // The (pattern, nogood id) pairs that mention each cell. A nogood only
+    // moves towards Unit or Violated when one of its cells gets its pattern.
+    by_cell: Vec<Vec<(PatternIndex, usize)>>,
+    // Nogoods learned since the last `affected` call.
+    unchecked: Vec<usize>,
+
+    // Cells whose bans are still to be explained while analyzing a conflict.
+    marks: CellCollection<bool>,
+    marked: Vec<CellIndex>,
+}
+
+impl Nogoods {
+    pub fn new(n_cells: usize, max_nogoods: usize) -> Self {
+        Self {
+            nogoods: Vec::new(),
+            max_nogoods,
+            by_cell: vec![Vec::new(); n_cells],
+            unchecked: Vec::new(),
+            marks: CellCollection::new_with_value(n_cells, false),
+            marked: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.max_nogoods > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.nogoods.len()
+    }
+
+    pub fn get(&self, id: usize) -> &[(CellIndex, PatternIndex)] {
+        &self.nogoods[id]
+    }
+
+    /// Stores `assignments` unless the cap is reached. Ids stay valid until
+    /// `clear`, since the trail refers to them.
+    pub fn learn(&mut self, assignments: Vec<(CellIndex, PatternIndex)>) -> Option<usize> {
+        if assignments.is_empty() || self.nogoods.len() >= self.max_nogoods {
+            return None;
+        }
+
+        let id = self.nogoods.len();
+        for &(cell, pattern) in &assignments {
+            self.by_cell[cell.base].push((pattern, id));
+        }
+
+        self.nogoods.push(assignments);
+        self.unchecked.push(id);
+        Some(id)
+    }
+
+    pub fn clear(&mut self) {
+        for nogood in self.nogoods.drain(..) {
+            for (cell, _) in nogood {
+                self.by_cell[cell.base].clear();
+            }
+        }
+        self.unchecked.clear();
+    }
+
+    /// The nogoods whose state may have changed since the last call: the new
+    /// ones, and those expecting the pattern one of the `determined` cells got.
+    pub fn affected(&mut self, determined: &[CellIndex], observed: &CellCollection<i32>) -> Vec<usize> {
+        let mut ids: Vec<usize> = self.unchecked.drain(..).collect();
+
+        for &cell in determined {
+            let matching = self.by_cell[cell.base]
+                .iter()
+                .filter(|&&(pattern, _)| observed[cell] == pattern.base as i32)
+                .map(|&(_, id)| id);
+
+            ids.extend(matching);
+        }
+
+        ids
+    }
+
+    /// Queues every nogood for the next `affected` call, for states the
+    /// nogoods learned since have never been checked against.
+    pub fn recheck_all(&mut self) {
+        self.unchecked.clear();
+        self.unchecked.extend(0..self.nogoods.len());
+    }
+
+    /// A cell is set when `observed` holds its pattern.
+    pub fn state(&self, id: usize, wave: &Wave, observed: &CellCollection<i32>) -> NogoodState {
+        let mut open = None;
+
+        for &(cell, pattern) in &self.nogoods[id] {
+            if observed[cell] == pattern.base as i32 {
+                continue;
+            }
+            if !wave.is_candidate(cell, pattern) || open.is_some() {
+                return NogoodState::Free;
+            }
+            open = Some((cell, pattern));
+        }
+
+        match open {
+            Some((cell, pattern)) => NogoodState::Unit(cell, pattern),
+            None => NogoodState::Violated,
+        }
+    }
+
+    /// Traces the bans on the cells in `conflict` back through the trail and
+    /// returns the decisions they depend on, as the `trail.decisions()` indices.
+    /// A ban that came from a neighbor is explained by every earlier ban on that
+    /// neighbor, which may blame more decisions than strictly needed but never
+    /// too few.
+    pub fn explain(&mut self, trail: &Trail, conflict: &[CellIndex]) -> Vec<usize> {
+        let decisions = trail.decisions();
+        let mut involved = vec![false; decisions.len()];
+
+        for &cell in conflict {
+            self.mark(cell);
+        }
+
+        // Bans before the oldest decision can't be undone, so they need no explaining.
+        let first = decisions.front().map_or(trail.trail_len(), |d| d.trail_len);
+        let mut level = decisions.len();
+
+        for i in (first..trail.trail_len()).rev() {
+            while level > 0 && decisions[level - 1].trail_len > i {
+                level -= 1;
+            }
+
+            let entry = trail.ban(i);
+            if !self.marks[entry.cell] {
+                continue;
+            }
+
+            match entry.reason {
+                Reason::Decision => involved[level - 1] = true,
+                Reason::Fixed => {}
+                Reason::Support(from) => self.mark(from),
+                Reason::Nogood(id) => {
+                    for k in 0..self.nogoods[id].len() {
+                        let (cell, _) = self.nogoods[id][k];
+                        self.mark(cell);
+                    }
+                }
+                Reason::Unknown => involved[..level].fill(true),
+            }
+        }
+
+        for cell in self.marked.drain(..) {
+            self.marks[cell] = false;
+        }
+
+        (0..decisions.len()).filter(|&k| involved[k]).collect()
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        let entry_size = std::mem::size_of::<(CellIndex, PatternIndex)>();
+        let stored: usize = self.nogoods.iter().map(|n| n.capacity() * entry_size).sum();
+
+        let index_size = std::mem::size_of::<(PatternIndex, usize)>();
+        let index: usize = self.by_cell.iter().map(|ids| ids.capacity() * index_size).sum::<usize>()
+            + self.by_cell.capacity() * std::mem::size_of::<Vec<(PatternIndex, usize)>>();
+
+        stored
+            + index
+            + self.unchecked.capacity() * std::mem::size_of::<usize>()
+            + self.marks.memory_usage_bytes()
+            + self.marked.capacity() * std::mem::size_of::<CellIndex>()
+    }
+
+    fn mark(&mut self, cell: CellIndex) {
+        if !self.marks[cell] {
+            self.marks[cell] = true;
+            self.marked.push(cell);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::cell_collection::CellCollection;
+
+const NOT_QUEUED: usize = usize::MAX;
+
+/// A cell's observation score, then the tie-break used only between equal scores.
+pub type Key = (f64, f64);
+
+/// An indexed binary min-heap of cells keyed on their observation score. The
+/// lowest key is read in O(1), and changing or removing the key of any queued
+/// cell costs O(log n).
+#[derive(Clone)]
+pub struct ObservationQueue {
+    // (key, cell) pairs in heap order.
+    heap: Vec<(Key, CellIndex)>,
+
+    // Where each cell sits in `heap`, or NOT_QUEUED.
+    position: CellCollection<usize>,
+}
+
+impl ObservationQueue {
+    pub fn new(n_cells: usize) -> Self {
+        Self {
+            heap: Vec::with_capacity(n_cells),
+            position: CellCollection::new_with_value(n_cells, NOT_QUEUED),
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, cell: CellIndex) -> bool {
+        self.position[cell] != NOT_QUEUED
+    }
+
+    /// The cell with the lowest score.
+    #[inline(always)]
+    pub fn peek(&self) -> Option<CellIndex> {
+        self.heap.first().map(|&(_, cell)| cell)
+    }
+
+    /// The queued cells, in no particular order.
+    pub fn cells(&self) -> impl Iterator<Item = CellIndex> + '_ {
+        self.heap.iter().map(|&(_, cell)| cell)
+    }
+
+    pub fn clear(&mut self) {
+        for &(_, cell) in &self.heap {
+            self.position[cell] = NOT_QUEUED;
+        }
+        self.heap.clear();
+    }
+
+    /// Replaces the contents of the queue, heapifying in O(n).
+    pub fn rebuild<I>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (Key, CellIndex)>,
+    {
+        self.clear();
+
+        for (key, cell) in entries {
+            if !self.contains(cell) {
+                self.position[cell] = self.heap.len();
+                self.heap.push((key, cell));
+            }
+        }
+
+        for i in (0..self.heap.len() / 2).rev() {
+            self.sift_down(i);
+        }
+    }
+
+    /// Queues a cell that is not queued yet.
+    pub fn insert(&mut self, cell: CellIndex, key: Key) {
+        let i = self.heap.len();
+
+        self.position[cell] = i;
+        self.heap.push((key, cell));
+        self.sift_up(i);
+    }
+
+    /// Moves a queued cell to its place for `key`.
+    pub fn update(&mut self, cell: CellIndex, key: Key) {
+        let i = self.position[cell];
+        let previous = self.heap[i].0;
+
+        self.heap[i].0 = key;
+        if precedes(key, previous) {
+            self.sift_up(i);
+        } else {
+            self.sift_down(i);
+        }
+    }
+
+    pub fn remove(&mut self, cell: CellIndex) {
+        let i = self.position[cell];
+        let last = self.heap.len() - 1;
+
+        self.swap(i, last);
+        self.heap.pop();
+        self.position[cell] = NOT_QUEUED;
+
+        if i < last {
+            let i = self.sift_up(i);
+            self.sift_down(i);
+        }
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        let heap_bytes = self.heap.capacity() * std::mem::size_of::<(Key, CellIndex)>();
+
+        heap_bytes + self.position.memory_usage_bytes()
+    }
+
+    // Returns the final position of the entry.
+    fn sift_up(&mut self, mut i: usize) -> usize {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !precedes(self.heap[i].0, self.heap[parent].0) {
+                break;
+            }
+
+            self.swap(i, parent);
+            i = parent;
+        }
+        i
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        let len = self.heap.len();
+
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            let mut smallest = i;
+
+            if left < len && precedes(self.heap[left].0, self.heap[smallest].0) {
+                smallest = left;
+            }
+            if right < len && precedes(self.heap[right].0, self.heap[smallest].0) {
+                smallest = right;
+            }
+            if smallest == i {
+                break;
+            }
+
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+
+    #[inline(always)]
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.position[self.heap[i].1] = i;
+        self.position[self.heap[j].1] = j;
+    }
+}
+
+#[inline(always)]
+fn precedes(a: Key, b: Key) -> bool {
+    a.0 < b.0 || (a.0 == b.0 && a.1 < b.1)
+}
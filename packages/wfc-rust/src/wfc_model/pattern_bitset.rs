@@ -8,7 +8,7 @@ pub struct PatternBitSet {
 
 impl PatternBitSet {
     pub fn new(t_count: usize) -> Self {
-        let words = (t_count + 63) / 64;
+        let words = t_count.div_ceil(64);
         let data = vec![0; words];
 
         Self { data, t_count }
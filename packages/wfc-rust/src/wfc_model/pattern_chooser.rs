@@ -0,0 +1,155 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::direction::DirectionIndex;
+use crate::wfc_model::entropy_tracker::EntropyTracker;
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::propagator::Propagator;
+use crate::wfc_model::topology::Topology;
+use crate::wfc_model::wave::Wave;
+
+/// What a `PatternChooser` may look at when picking a pattern.
+pub struct ChoiceContext<'a> {
+    pub wave: &'a Wave,
+    pub entropy_tracker: &'a EntropyTracker,
+    pub propagator: &'a Propagator,
+    pub topology: &'a dyn Topology,
+}
+
+/// Decides which of its remaining patterns an observed cell collapses to.
+pub trait PatternChooser {
+    /// `roll` is a uniform draw in 0.0..1.0 from the solver's PRNG, made for
+    /// every observation whether the chooser uses it or not.
+    fn choose(&self, cell: CellIndex, roll: f64, context: &ChoiceContext) -> PatternIndex;
+}
+
+/// The built-in [`PatternChooser`]s.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PatternChoice {
+    /// Random, in proportion to the pattern weights.
+    WeightedRandom,
+
+    /// Always the heaviest pattern left, the lowest index on ties.
+    MaxWeight,
+
+    /// The pattern that bans the fewest candidates of the neighboring cells,
+    /// weighted random on ties.
+    LeastConstraining,
+
+    /// Random, in proportion to `weight^(1 / temperature)`. Below 1 favors heavy
+    /// patterns, above 1 flattens the weights towards uniform.
+    Temperature,
+}
+
+pub fn build_chooser(choice: PatternChoice, weights: &[f64], temperature: f64) -> Box<dyn PatternChooser> {
+    match choice {
+        PatternChoice::WeightedRandom => Box::new(WeightedRandom),
+        PatternChoice::MaxWeight => Box::new(MaxWeight),
+        PatternChoice::LeastConstraining => Box::new(LeastConstraining),
+        PatternChoice::Temperature => Box::new(Tempered {
+            weights: weights.iter().map(|w| w.powf(1.0 / temperature)).collect(),
+        }),
+    }
+}
+
+pub struct WeightedRandom;
+
+impl PatternChooser for WeightedRandom {
+    fn choose(&self, cell: CellIndex, roll: f64, context: &ChoiceContext) -> PatternIndex {
+        context.wave.get_random_pattern(cell, roll, context.entropy_tracker)
+    }
+}
+
+pub struct MaxWeight;
+
+impl PatternChooser for MaxWeight {
+    fn choose(&self, cell: CellIndex, _roll: f64, context: &ChoiceContext) -> PatternIndex {
+        let mut best = None;
+        let mut best_weight = f64::NEG_INFINITY;
+
+        for p in context.wave.candidates(cell) {
+            let weight = context.entropy_tracker.get_pattern_weight(p);
+            if weight > best_weight {
+                best_weight = weight;
+                best = Some(p);
+            }
+        }
+
+        best.unwrap_or(PatternIndex { base: 0 })
+    }
+}
+
+pub struct LeastConstraining;
+
+impl LeastConstraining {
+    // Neighbor candidates that could no longer sit next to `cell` once it holds `pattern`.
+    // Counted a word of the wave at a time, so a choice costs O(T * D * T / 64).
+    fn ban_count(&self, cell: CellIndex, pattern: PatternIndex, context: &ChoiceContext) -> usize {
+        let mut bans = 0;
+
+        for d_idx in 0..context.topology.direction_count() {
+            let d = DirectionIndex { base: d_idx };
+
+            if let Some(neighbor) = context.topology.neighbor(cell, d) {
+                let kept = context.wave.candidate_count_in(neighbor, context.propagator.get_mask(pattern, d));
+
+                bans += context.wave.candidate_count(neighbor) - kept;
+            }
+        }
+
+        bans
+    }
+}
+
+impl PatternChooser for LeastConstraining {
+    fn choose(&self, cell: CellIndex, roll: f64, context: &ChoiceContext) -> PatternIndex {
+        let counts: Vec<(PatternIndex, usize)> = context
+            .wave
+            .candidates(cell)
+            .map(|p| (p, self.ban_count(cell, p, context)))
+            .collect();
+        let fewest = counts.iter().map(|&(_, bans)| bans).min().unwrap_or(0);
+        let tied = counts.iter().filter(|&&(_, bans)| bans == fewest).map(|&(p, _)| p);
+
+        pick_weighted(tied, roll, |p| context.entropy_tracker.get_pattern_weight(p))
+    }
+}
+
+pub struct Tempered {
+    // Pattern weights raised to 1 / temperature.
+    weights: Vec<f64>,
+}
+
+impl PatternChooser for Tempered {
+    fn choose(&self, cell: CellIndex, roll: f64, context: &ChoiceContext) -> PatternIndex {
+        pick_weighted(context.wave.candidates(cell), roll, |p| self.weights[p.base])
+    }
+}
+
+// Picks from `patterns` in proportion to `weight`, falling back to the last one
+// when rounding leaves the roll unspent.
+fn pick_weighted<I, W>(patterns: I, roll: f64, weight: W) -> PatternIndex
+where
+    I: Iterator<Item = PatternIndex> + Clone,
+    W: Fn(PatternIndex) -> f64,
+{
+    let total: f64 = patterns.clone().map(&weight).sum();
+    let mut x = roll * total;
+    let mut last = PatternIndex { base: 0 };
+
+    for p in patterns {
+        let w = weight(p);
+
+        last = p;
+        if w > 0.0 {
+            x -= w;
+            if x <= 0.0 {
+                return p;
+            }
+        }
+    }
+
+    last
+}
@@ -33,4 +33,10 @@ impl<T: Clone + Default> IndexMut<PatternIndex> for PatternCollection<T> {
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct PatternIndex {
    pub base: usize,
-}
\ No newline at end of file
+}
+
+impl PatternIndex {
+    pub fn new(base: usize) -> Self {
+        Self { base }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::wfc_model::direction::Direction;
+use crate::wfc_model::direction::DirectionIndex;
 use crate::wfc_model::pattern_collection::PatternIndex;
 use crate::wfc_model::pattern_bitset::PatternBitSet;
 
@@ -10,26 +10,21 @@ pub struct Propagator {
     masks: Vec<PatternBitSet>,
 }
 impl Propagator {
-    pub fn new(data: Vec<i32>, offsets: Vec<i32>, lengths: Vec<i32>, t_count: usize) -> Self {
-        let mut masks = Vec::with_capacity(t_count * 4);
+    pub fn new(adjacency: &[Vec<Vec<PatternIndex>>], t_count: usize, d_count: usize) -> Self {
+        let mut masks = Vec::with_capacity(t_count * d_count);
+        let mut lengths = Vec::with_capacity(t_count * d_count);
 
-        for d_idx in 0..4 {
-            for t_idx in 0..t_count {
+        for d_idx in 0..d_count {
+            for lists in adjacency.iter().take(t_count) {
                 let mut bitset = PatternBitSet::new(t_count);
-                let lookup_idx = (d_idx * t_count) + t_idx;
-                let start = offsets[lookup_idx] as usize;
-                let len = lengths[lookup_idx] as usize;
-                let end = start + len;
-                let valid_ids = &data[start..end];
-
-                for &id in valid_ids {
-
-                    let pattern_idx = PatternIndex{ base: id as usize };
+                let valid_ids = &lists[d_idx];
 
+                for &pattern_idx in valid_ids {
                     bitset.set(pattern_idx);
                 }
 
                 masks.push(bitset);
+                lengths.push(valid_ids.len() as i32);
             }
         }
 
@@ -41,31 +36,37 @@ impl Propagator {
     }
 
     #[inline(always)]
-    pub fn get_lookup_idx(&self, pattern: PatternIndex, direction: Direction) -> usize {
-        let d_val = direction as usize;
-        let result = d_val * self.t_count + pattern.base;
-
-        result
+    pub fn get_lookup_idx(&self, pattern: PatternIndex, direction: DirectionIndex) -> usize {
+        direction.base * self.t_count + pattern.base
     }
 
-    pub fn get_mask(&self, pattern: PatternIndex, direction: Direction) -> &PatternBitSet {
+    pub fn get_mask(&self, pattern: PatternIndex, direction: DirectionIndex) -> &PatternBitSet {
         let idx = self.get_lookup_idx(pattern, direction);
-        let mask = &self.masks[idx];
 
-        mask
+        &self.masks[idx]
     }
 
-    pub fn get_compatible_count(&self, pattern: PatternIndex, direction: Direction) -> i32 {
+    pub fn get_compatible_count(&self, pattern: PatternIndex, direction: DirectionIndex) -> i32 {
         let idx = self.get_lookup_idx(pattern, direction);
-        let count = self.lengths[idx];
 
-        count
+        self.lengths[idx]
+    }
+
+    /// Whether `other` may sit in `direction` of `pattern`.
+    pub fn is_compatible(&self, pattern: PatternIndex, direction: DirectionIndex, other: PatternIndex) -> bool {
+        if other.base >= self.t_count {
+            return false;
+        }
+
+        let mask = self.get_mask(pattern, direction);
+
+        (mask.data[other.base >> 6] >> (other.base & 63)) & 1 == 1
     }
 
     pub fn for_each_compatible_pattern<F>(
         &self,
         pattern: PatternIndex,
-        direction: Direction,
+        direction: DirectionIndex,
         mut f: F,
     ) where
         F: FnMut(PatternIndex),
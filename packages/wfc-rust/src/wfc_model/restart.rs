@@ -0,0 +1,77 @@
+use crate::wfc_model::error::WfcError;
+use crate::wfc_model::rng::Rng;
+
+/// How many attempts [`Solver::run_with_restarts`] makes and how many steps each
+/// may take before it is abandoned.
+///
+/// [`Solver::run_with_restarts`]: crate::Solver::run_with_restarts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestartPolicy {
+    /// Every attempt runs until it succeeds or fails.
+    Fixed { attempts: usize },
+
+    /// Attempt `i` may take `unit` times the `i`th term of the Luby sequence
+    /// 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ... steps.
+    Luby { unit: usize, attempts: usize },
+
+    /// The first attempt may take `initial` steps, and each one after it
+    /// `factor` times as many as the one before. `initial` must be positive
+    /// and `factor` finite and at least 1.
+    Geometric { initial: usize, factor: f64, attempts: usize },
+}
+
+impl RestartPolicy {
+    pub fn max_attempts(&self) -> usize {
+        match *self {
+            RestartPolicy::Fixed { attempts }
+            | RestartPolicy::Luby { attempts, .. }
+            | RestartPolicy::Geometric { attempts, .. } => attempts,
+        }
+    }
+
+    /// Rejects geometric budgets that start at zero, shrink, or are not numbers.
+    pub fn check(&self) -> Result<(), WfcError> {
+        if let RestartPolicy::Geometric { initial, factor, .. } = *self {
+            if initial == 0 || !factor.is_finite() || factor < 1.0 {
+                return Err(WfcError::InvalidRestartPolicy { policy: *self });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Steps allowed for the zero-based `attempt`. Never less than one.
+    pub fn step_budget(&self, attempt: usize) -> usize {
+        let budget = match *self {
+            RestartPolicy::Fixed { .. } => usize::MAX,
+            RestartPolicy::Luby { unit, .. } => unit.saturating_mul(luby(attempt + 1)),
+            RestartPolicy::Geometric { initial, factor, .. } => {
+                // Float to int casts saturate
+                (initial as f64 * factor.powi(attempt as i32)) as usize
+            }
+        };
+
+        budget.max(1)
+    }
+}
+
+/// The seed for the zero-based `attempt` of a run started with `seed`.
+pub fn attempt_seed(seed: u64, attempt: usize) -> u64 {
+    Rng::new(seed.wrapping_add(attempt as u64)).next_u64()
+}
+
+// The `i`th term of the Luby sequence, counting from 1.
+fn luby(mut i: usize) -> usize {
+    loop {
+        // The smallest k with 2^k - 1 >= i
+        let mut k = 1;
+        while (1usize << k) - 1 < i {
+            k += 1;
+        }
+
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
@@ -0,0 +1,39 @@
+// SplitMix64: a single u64 of state, so the full generator state can be
+// exported to and restored from JS as one value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    #[inline(always)]
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in 0.0..1.0 built from the top 53 bits.
+    #[inline(always)]
+    pub fn next_f64(&mut self) -> f64 {
+        let bits = self.next_u64() >> 11;
+
+        (bits as f64) * (1.0 / (1u64 << 53) as f64)
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: u64) {
+        self.state = state;
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::VecDeque;
+
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::pattern_collection::PatternIndex;
+
+/// When `Backtracking::Snapshot` copies the wave, and which copies it keeps once
+/// `max_snapshots` or the byte budget is reached. The newest snapshot is always
+/// kept, even when it alone is over the byte budget, and the oldest as long as
+/// it fits alongside the newest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnapshotPolicy {
+    /// Every `snapshot_interval_percent` of progress. Once full, snapshots are
+    /// dropped so that the rest stay evenly spread over the progress made.
+    Progress,
+
+    /// Every `n` observations. Once full, snapshots are dropped so that the
+    /// rest stay evenly spread over the observations made.
+    Decisions(usize),
+
+    /// Every observation. Once full, snapshots are thinned so that they are
+    /// dense near the newest observation and exponentially sparser with depth.
+    Exponential,
+}
+
+/// A snapshot as it is taken and restored.
+#[derive(Clone)]
+pub struct WaveSnapshot {
+    pub(crate) wave_data: Vec<u64>,
+    pub(crate) cells_collapsed_indices: Vec<CellIndex>,
+    pub(crate) tried_pattern: PatternIndex,
+    pub(crate) target_cell: CellIndex,
+
+    // Observations made before this one, and the filled fraction at the time.
+    pub(crate) decision: usize,
+    pub(crate) progress: f64,
+}
+
+// A snapshot as it is stored: its wave words followed by a bitset of its
+// uncollapsed cells, either in full or as a delta.
+#[derive(Clone)]
+enum Frame {
+    Key(Vec<u64>),
+    Delta(Delta),
+}
+
+// The words that differ from the previous snapshot's frame, as XOR masks in
+// runs of consecutive words. XOR makes a delta its own inverse, so it also
+// turns the frame after it back into the one before.
+#[derive(Clone)]
+struct Delta {
+    // (first word, word count)
+    runs: Vec<(u32, u32)>,
+    masks: Vec<u64>,
+}
+
+impl Delta {
+    fn between(previous: &[u64], current: &[u64]) -> Self {
+        let changes = previous.iter().zip(current).map(|(a, b)| a ^ b).enumerate();
+
+        Self::from_changes(changes)
+    }
+
+    // Takes (word, mask) pairs in ascending word order and skips the masks
+    // that change nothing.
+    fn from_changes<I: Iterator<Item = (usize, u64)>>(changes: I) -> Self {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        let mut masks = Vec::new();
+
+        for (i, mask) in changes.filter(|&(_, mask)| mask != 0) {
+            match runs.last_mut() {
+                Some((start, len)) if (*start + *len) as usize == i => *len += 1,
+                _ => runs.push((i as u32, 1)),
+            }
+            masks.push(mask);
+        }
+
+        runs.shrink_to_fit();
+        masks.shrink_to_fit();
+        Self { runs, masks }
+    }
+
+    fn changes(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let words = self.runs.iter().flat_map(|&(start, len)| start as usize..(start + len) as usize);
+
+        words.zip(self.masks.iter().copied())
+    }
+
+    fn apply_to(&self, frame: &mut [u64]) {
+        for (i, mask) in self.changes() {
+            frame[i] ^= mask;
+        }
+    }
+
+    // The delta from the frame before `self` to the frame after `next`.
+    fn merge(&self, next: &Delta) -> Self {
+        let mut a = self.changes().peekable();
+        let mut b = next.changes().peekable();
+
+        let merged = std::iter::from_fn(|| match (a.peek().copied(), b.peek().copied()) {
+            (Some((i, x)), Some((j, y))) if i == j => {
+                a.next();
+                b.next();
+                Some((i, x ^ y))
+            }
+            (Some((i, _)), Some((j, _))) if j < i => b.next(),
+            (Some(_), _) => a.next(),
+            (None, _) => b.next(),
+        });
+
+        Self::from_changes(merged)
+    }
+}
+
+impl Frame {
+    fn is_key(&self) -> bool {
+        matches!(self, Frame::Key(_))
+    }
+
+    fn memory_usage_bytes(&self) -> usize {
+        match self {
+            Frame::Key(words) => words.capacity() * std::mem::size_of::<u64>(),
+            Frame::Delta(delta) => {
+                delta.runs.capacity() * std::mem::size_of::<(u32, u32)>()
+                    + delta.masks.capacity() * std::mem::size_of::<u64>()
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct StoredSnapshot {
+    frame: Frame,
+    tried_pattern: PatternIndex,
+    target_cell: CellIndex,
+    decision: usize,
+    progress: f64,
+}
+
+impl StoredSnapshot {
+    fn memory_usage_bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.frame.memory_usage_bytes()
+    }
+}
+
+/// The snapshots of `Backtracking::Snapshot`, oldest first. Every
+/// `keyframe_interval`th one is stored in full and the ones between as deltas
+/// against the snapshot before them, so restoring one replays at most
+/// `keyframe_interval - 1` deltas.
+#[derive(Clone)]
+pub struct SnapshotHistory {
+    snapshots: VecDeque<StoredSnapshot>,
+    policy: SnapshotPolicy,
+    max_snapshots: usize,
+    interval_percent: f64,
+    keyframe_interval: usize,
+    n_cells: usize,
+
+    // 0 for no byte limit.
+    max_bytes: usize,
+    bytes: usize,
+
+    // The full frame of the newest snapshot, which the next delta is taken against.
+    latest: Vec<u64>,
+}
+
+impl SnapshotHistory {
+    pub fn new(
+        n_cells: usize,
+        policy: SnapshotPolicy,
+        max_snapshots: usize,
+        interval_percent: f64,
+        max_bytes: usize,
+        keyframe_interval: usize,
+    ) -> Self {
+        Self {
+            snapshots: VecDeque::with_capacity(max_snapshots),
+            policy,
+            max_snapshots,
+            interval_percent,
+            keyframe_interval,
+            n_cells,
+            max_bytes,
+            bytes: 0,
+            latest: Vec::new(),
+        }
+    }
+
+    /// Whether the observation numbered `decision`, made at `progress`, should be saved.
+    pub fn is_due(&self, decision: usize, progress: f64) -> bool {
+        if self.max_snapshots == 0 {
+            return false;
+        }
+
+        let last = match self.snapshots.back() {
+            Some(last) => last,
+            None => return true,
+        };
+
+        match self.policy {
+            SnapshotPolicy::Progress => progress - last.progress >= self.interval_percent,
+            SnapshotPolicy::Decisions(n) => decision - last.decision >= n,
+            SnapshotPolicy::Exponential => true,
+        }
+    }
+
+    pub fn push(&mut self, snapshot: WaveSnapshot) {
+        let frame = self.pack(&snapshot);
+        let deltas = self.snapshots.iter().rev().take_while(|s| !s.frame.is_key()).count();
+
+        let mut stored = Frame::Key(frame.clone());
+        if !self.snapshots.is_empty() && deltas + 1 < self.keyframe_interval {
+            let delta = Frame::Delta(Delta::between(&self.latest, &frame));
+
+            // A delta touching most of the frame saves nothing
+            if delta.memory_usage_bytes() < stored.memory_usage_bytes() {
+                stored = delta;
+            }
+        }
+        self.latest = frame;
+
+        let stored = StoredSnapshot {
+            frame: stored,
+            tried_pattern: snapshot.tried_pattern,
+            target_cell: snapshot.target_cell,
+            decision: snapshot.decision,
+            progress: snapshot.progress,
+        };
+
+        self.bytes += stored.memory_usage_bytes();
+        self.snapshots.push_back(stored);
+        self.thin();
+    }
+
+    pub fn pop(&mut self) -> Option<WaveSnapshot> {
+        let stored = self.snapshots.pop_back()?;
+        let frame = std::mem::take(&mut self.latest);
+
+        self.bytes -= stored.memory_usage_bytes();
+        self.latest = match &stored.frame {
+            Frame::Delta(delta) => {
+                let mut previous = frame.clone();
+                delta.apply_to(&mut previous);
+                previous
+            }
+            Frame::Key(_) => self.unpack_frame(self.snapshots.len()),
+        };
+
+        let snapshot = self.unpack(frame, &stored);
+        Some(snapshot)
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.latest.clear();
+        self.bytes = 0;
+    }
+
+    /// Moves the snapshots out, leaving an empty history with the same settings.
+    pub fn take(&mut self) -> Self {
+        let empty = Self::new(
+            self.n_cells,
+            self.policy,
+            self.max_snapshots,
+            self.interval_percent,
+            self.max_bytes,
+            self.keyframe_interval,
+        );
+
+        std::mem::replace(self, empty)
+    }
+
+    pub fn set_policy(&mut self, policy: SnapshotPolicy, max_bytes: usize) {
+        self.policy = policy;
+        self.max_bytes = max_bytes;
+
+        self.thin();
+    }
+
+    /// Only applies to the snapshots taken from now on.
+    pub fn set_keyframe_interval(&mut self, keyframe_interval: usize) {
+        self.keyframe_interval = keyframe_interval;
+    }
+
+    /// The observation numbers the snapshots were taken at, oldest first.
+    pub fn decisions(&self) -> Vec<usize> {
+        self.snapshots.iter().map(|s| s.decision).collect()
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bytes + self.latest.capacity() * std::mem::size_of::<u64>()
+    }
+
+    fn bitset_words(&self) -> usize {
+        self.n_cells.div_ceil(64)
+    }
+
+    fn pack(&self, snapshot: &WaveSnapshot) -> Vec<u64> {
+        let wave_words = snapshot.wave_data.len();
+        let mut frame = Vec::with_capacity(wave_words + self.bitset_words());
+
+        frame.extend_from_slice(&snapshot.wave_data);
+        frame.resize(wave_words + self.bitset_words(), 0);
+        for &cell in &snapshot.cells_collapsed_indices {
+            frame[wave_words + cell.base / 64] |= 1 << (cell.base % 64);
+        }
+
+        frame
+    }
+
+    fn unpack(&self, mut frame: Vec<u64>, stored: &StoredSnapshot) -> WaveSnapshot {
+        let wave_words = frame.len() - self.bitset_words();
+        let cells_collapsed_indices = (0..self.n_cells)
+            .filter(|&i| frame[wave_words + i / 64] & (1 << (i % 64)) != 0)
+            .map(|base| CellIndex { base })
+            .collect();
+        frame.truncate(wave_words);
+
+        WaveSnapshot {
+            wave_data: frame,
+            cells_collapsed_indices,
+            tried_pattern: stored.tried_pattern,
+            target_cell: stored.target_cell,
+            decision: stored.decision,
+            progress: stored.progress,
+        }
+    }
+
+    // The full frame of snapshot `len - 1`, replayed from the keyframe before
+    // it. The oldest snapshot is always a keyframe.
+    fn unpack_frame(&self, len: usize) -> Vec<u64> {
+        let key = self.snapshots.range(..len).rposition(|s| s.frame.is_key()).unwrap_or(0);
+        let mut frame = Vec::new();
+
+        for s in self.snapshots.range(key..len) {
+            match &s.frame {
+                Frame::Key(words) => frame.clone_from(words),
+                Frame::Delta(delta) => delta.apply_to(&mut frame),
+            }
+        }
+
+        frame
+    }
+
+    fn is_over_budget(&self) -> bool {
+        self.snapshots.len() > self.max_snapshots || (self.max_bytes > 0 && self.bytes > self.max_bytes)
+    }
+
+    // Drops snapshots from the middle until the history fits again. The oldest
+    // only goes once the oldest and the newest alone are over budget, and the
+    // newest never does.
+    fn thin(&mut self) {
+        while self.is_over_budget() && self.snapshots.len() > 2 {
+            let i = self.cheapest_to_drop();
+            self.remove(i);
+        }
+
+        while self.is_over_budget() && self.snapshots.len() > 1 {
+            self.remove(0);
+        }
+    }
+
+    // Removes any snapshot but the newest, folding its delta or keyframe into
+    // the next one so that the next one restores the same wave.
+    fn remove(&mut self, i: usize) {
+        let removed = match self.snapshots.remove(i) {
+            Some(removed) => removed,
+            None => return,
+        };
+        self.bytes -= removed.memory_usage_bytes();
+
+        let next = &mut self.snapshots[i];
+        let folded = match (&removed.frame, &next.frame) {
+            (_, Frame::Key(_)) => return,
+            (Frame::Key(words), Frame::Delta(delta)) => {
+                let mut words = words.clone();
+                delta.apply_to(&mut words);
+                Frame::Key(words)
+            }
+            (Frame::Delta(first), Frame::Delta(second)) => Frame::Delta(first.merge(second)),
+        };
+
+        self.bytes -= next.memory_usage_bytes();
+        next.frame = folded;
+        self.bytes += next.memory_usage_bytes();
+    }
+
+    // The inner snapshot whose removal leaves the smallest gap between its
+    // neighbors. Under `Exponential` the gap is weighed against how far the
+    // snapshot lies behind the newest, so older gaps may grow larger.
+    fn cheapest_to_drop(&self) -> usize {
+        let position = |s: &StoredSnapshot| match self.policy {
+            SnapshotPolicy::Progress => s.progress,
+            _ => s.decision as f64,
+        };
+        let frontier = position(&self.snapshots[self.snapshots.len() - 1]);
+
+        let mut best = 1;
+        let mut best_cost = f64::INFINITY;
+
+        for i in 1..self.snapshots.len() - 1 {
+            let gap = position(&self.snapshots[i + 1]) - position(&self.snapshots[i - 1]);
+            let cost = match self.policy {
+                SnapshotPolicy::Exponential => gap / (frontier - position(&self.snapshots[i])),
+                _ => gap,
+            };
+
+            if cost < best_cost {
+                best = i;
+                best_cost = cost;
+            }
+        }
+
+        best
+    }
+}
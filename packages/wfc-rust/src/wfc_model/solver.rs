@@ -0,0 +1,1476 @@
+use crate::wfc_model::boundary::{Boundary, BoundaryMode, SideSpec};
+use crate::wfc_model::cell::Cell;
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::cell_collapsed_collection::CellCollapsedCollection;
+use crate::wfc_model::cell_collection::CellCollection;
+use crate::wfc_model::cell_selector::{build_selector, CellSelector};
+use crate::wfc_model::compatible::Compatible;
+use crate::wfc_model::config::WfcConfig;
+use crate::wfc_model::constraints::Constraints;
+use crate::wfc_model::direction::{Direction, DirectionIndex};
+use crate::wfc_model::dirty_cells::DirtyCells;
+use crate::wfc_model::entropy_tracker::EntropyTracker;
+use crate::wfc_model::error::WfcError;
+use crate::wfc_model::graph_topology::GraphTopology;
+use crate::wfc_model::mask::{active_cells, apply_mask, Mask, MaskMode};
+use crate::wfc_model::nogoods::{NogoodState, Nogoods};
+use crate::wfc_model::pattern_chooser::{build_chooser, ChoiceContext, PatternChooser};
+use crate::wfc_model::pattern_collection::PatternIndex;
+use crate::wfc_model::propagator::Propagator;
+use crate::wfc_model::restart::{attempt_seed, RestartPolicy};
+use crate::wfc_model::rng::Rng;
+use crate::wfc_model::snapshot_history::{SnapshotHistory, SnapshotPolicy, WaveSnapshot};
+use crate::wfc_model::topology::{build_topology, GridShape, Topology};
+use crate::wfc_model::trail::{Backtracking, Reason, Trail};
+use crate::wfc_model::wave::Wave;
+use crate::wfc_model::work_budget::{WorkBudget, WorkLimit};
+use crate::wfc_model::{AttemptRecord, IterationResult, RunSummary};
+use std::f64;
+
+#[derive(Clone)]
+pub struct WFCState {
+    pub wave: Wave,
+    pub compatible: Compatible,
+    pub entropy_tracker: EntropyTracker,
+    pub observed: CellCollection<i32>,
+    pub dirty_cells: DirtyCells,
+    // Cells with bans the observation queue has not caught up with yet.
+    pub touched: DirtyCells,
+    pub stack: Vec<(CellIndex, PatternIndex)>,
+    // Cells set since learned nogoods were last checked against the wave.
+    pub determined: Vec<CellIndex>,
+
+    // Bans since the oldest decision that can be undone. Not used by `Backtracking::Snapshot`.
+    pub trail: Trail,
+
+    // Total bans ever made. Only differences are meaningful; used for work budgets.
+    pub ban_count: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Propagation {
+    Done,
+    Paused,
+    Contradiction,
+}
+
+impl WFCState {
+    pub fn ban(&mut self, cell_idx: CellIndex, pattern_idx: PatternIndex, reason: Reason) {
+        if !self.wave.is_candidate(cell_idx, pattern_idx) {
+            return;
+        }
+
+        let previous = self.entropy_tracker.cell_weights(cell_idx);
+        self.trail.record(cell_idx, pattern_idx, reason, previous);
+        self.wave.eliminate_candidate(cell_idx, pattern_idx);
+        self.dirty_cells.mark_dirty(cell_idx);
+        self.touched.mark_dirty(cell_idx);
+        self.entropy_tracker.ban_pattern(cell_idx, pattern_idx);
+
+        if self.entropy_tracker.pattern_determined(cell_idx) {
+            let remaining = self.wave.find_remaining_pattern(cell_idx);
+            self.observed[cell_idx] = remaining;
+            self.determined.push(cell_idx);
+        }
+
+        self.stack.push((cell_idx, pattern_idx));
+        self.ban_count += 1;
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        let stack_bytes = self.stack.capacity() * std::mem::size_of::<(CellIndex, PatternIndex)>();
+        let determined_bytes = self.determined.capacity() * std::mem::size_of::<CellIndex>();
+
+        self.wave.memory_usage_bytes()
+            + self.compatible.memory_usage_bytes()
+            + self.entropy_tracker.memory_usage_bytes()
+            + self.observed.memory_usage_bytes()
+            + self.dirty_cells.memory_usage_bytes()
+            + self.touched.memory_usage_bytes()
+            + stack_bytes
+            + determined_bytes
+            + self.trail.memory_usage_bytes()
+    }
+}
+
+// What an edit that ends in a contradiction rolls back to.
+struct Checkpoint {
+    state: WFCState,
+    cells_collapsed: CellCollapsedCollection,
+    nogoods: Nogoods,
+    conflict: Vec<CellIndex>,
+    to_ban_queue: Vec<(CellIndex, PatternIndex, Reason)>,
+    decision_count: usize,
+    generation_complete: bool,
+    failed: bool,
+}
+
+/// The WFC solver itself, independent of the JS bindings. Build one from a
+/// [`WfcConfig`] and call [`Solver::step`] until it returns `SUCCESS` or `FAIL`.
+pub struct Solver {
+    width: usize,
+    height: usize,
+    depth: usize,
+    n_cells: usize,
+    boundary: Boundary,
+    backtracking: Backtracking,
+    max_snapshots: usize,
+
+    cell: Cell,
+    shape: GridShape,
+    // Used instead of `shape` when set. Kept unmasked so that the mask can be replaced.
+    graph: Option<GraphTopology>,
+    mask: Option<Mask>,
+    topology: Box<dyn Topology>,
+    cells_collapsed: CellCollapsedCollection,
+    history: SnapshotHistory,
+    // Observations made so far, not counting the undone ones.
+    decision_count: usize,
+    propagator: Propagator,
+    selector: Box<dyn CellSelector>,
+    chooser: Box<dyn PatternChooser>,
+    rng: Rng,
+    // The seed given to `new` or `reseed`, which restarts derive theirs from.
+    seed: u64,
+    attempt_log: Vec<AttemptRecord>,
+    constraints: Constraints,
+
+    // Edge bans derived from `boundary`, kept apart from the user's constraints.
+    boundary_bans: Constraints,
+    // Bans next to masked cells under `MaskMode::Void`.
+    void_bans: Constraints,
+
+    state: WFCState,
+    nogoods: Nogoods,
+
+    // The cells left without a pattern by the last contradiction.
+    conflict: Vec<CellIndex>,
+
+    generation_complete: bool,
+    // Set once generation has failed, including when the persistent bans alone
+    // contradict each other. Only `clear` starts over.
+    failed: bool,
+    to_ban_queue: Vec<(CellIndex, PatternIndex, Reason)>,
+
+    t_count: usize,
+}
+
+impl Solver {
+    pub fn new(config: WfcConfig) -> Self {
+        let WfcConfig {
+            width,
+            height,
+            depth,
+            weights,
+            shape,
+            graph,
+            mask,
+            adjacency,
+            boundary_x,
+            boundary_y,
+            boundary_z,
+            edge_patterns,
+            sides,
+            start_bias,
+            start_x,
+            start_y,
+            start_z,
+            selection,
+            pattern_choice,
+            temperature,
+            backtracking,
+            max_snapshots,
+            snapshot_interval_percent,
+            snapshot_policy,
+            max_snapshot_bytes,
+            snapshot_keyframe_interval,
+            max_nogoods,
+            seed,
+        } = config;
+        let t_count = weights.len();
+
+        let mut edge_flags = vec![false; t_count];
+        for p in edge_patterns {
+            edge_flags[p.base] = true;
+        }
+
+        let boundary = Boundary {
+            x: boundary_x,
+            y: boundary_y,
+            z: boundary_z,
+            edge_patterns: edge_flags,
+            sides,
+        };
+        let topology: Box<dyn Topology> = match &graph {
+            Some(graph) => Box::new(graph.clone()),
+            None => build_topology(shape, width, height, depth, &boundary),
+        };
+        let propagator = Propagator::new(&adjacency, t_count, topology.direction_count());
+        let (topology, void_bans) = apply_mask(topology, mask.as_ref(), &propagator, t_count);
+        let n_cells = topology.cell_count();
+
+        let chooser = build_chooser(pattern_choice, &weights, temperature);
+        let state = WFCState {
+            wave: Wave::new(n_cells, t_count),
+            compatible: Compatible::new(n_cells, t_count, topology.direction_count(), &propagator),
+            entropy_tracker: EntropyTracker::new(n_cells, t_count, weights),
+            observed: CellCollection::new_with_value(n_cells, -1),
+            dirty_cells: DirtyCells::new(n_cells),
+            touched: DirtyCells::new(n_cells),
+            stack: Vec::with_capacity(n_cells * t_count),
+            determined: Vec::new(),
+            trail: Trail::new(),
+            ban_count: 0,
+        };
+
+        let cell = Cell::new(width, height);
+        let active = active_cells(mask.as_ref(), n_cells);
+
+        let mut rng = Rng::new(seed);
+        let size = [width, height, depth];
+        let mut selector = build_selector(selection, size, start_bias, [start_x, start_y, start_z]);
+
+        selector.reseed(&mut rng);
+
+        let mut solver = Self {
+            width,
+            height,
+            depth,
+            n_cells,
+            boundary,
+            backtracking,
+            max_snapshots,
+            cell,
+            shape,
+            graph,
+            mask,
+            topology,
+            cells_collapsed: CellCollapsedCollection::new(active, n_cells),
+            history: SnapshotHistory::new(
+                n_cells,
+                snapshot_policy,
+                max_snapshots,
+                snapshot_interval_percent,
+                max_snapshot_bytes,
+                snapshot_keyframe_interval,
+            ),
+            decision_count: 0,
+            propagator,
+            selector,
+            chooser,
+            rng,
+            seed,
+            attempt_log: Vec::new(),
+            constraints: Constraints::new(),
+            boundary_bans: Constraints::new(),
+            void_bans,
+            state,
+            nogoods: Nogoods::new(n_cells, max_nogoods),
+            conflict: Vec::new(),
+            t_count,
+            generation_complete: false,
+            failed: false,
+            to_ban_queue: Vec::with_capacity(1024),
+        };
+
+        solver.cells_collapsed.reset(&solver.state.entropy_tracker, &*solver.selector);
+        solver.observe_single_pattern();
+
+        // A boundary that cannot be satisfied shows up as FAIL on the first step.
+        solver.boundary_bans = solver.collect_edge_bans();
+        solver.apply_persistent_bans();
+        solver
+    }
+
+    fn take_snapshot(&mut self, i: CellIndex, t: PatternIndex) {
+        if self.max_snapshots == 0 {
+            return;
+        }
+        if self.backtracking.uses_trail() {
+            self.state.trail.push_decision(i, t, self.max_snapshots);
+            return;
+        }
+
+        let decision = self.decision_count;
+        let progress = self.filled_percent();
+        self.decision_count += 1;
+
+        if !self.history.is_due(decision, progress) {
+            return;
+        }
+
+        let snapshot = WaveSnapshot {
+            wave_data: self.state.wave.clone_data(),
+            cells_collapsed_indices: self.cells_collapsed.get_uncollapsed_cells(),
+            tried_pattern: t,
+            target_cell: i,
+            decision,
+            progress,
+        };
+
+        // A full history is thinned out rather than dropped from the front
+        self.history.push(snapshot);
+    }
+
+    pub fn propagate(&mut self) -> bool {
+        let mut never = || false;
+        let mut limit = WorkLimit::new(WorkBudget::UNLIMITED, self.state.ban_count, &mut never);
+
+        self.propagate_limited(&mut limit) != Propagation::Contradiction
+    }
+
+    fn propagate_limited(&mut self, limit: &mut WorkLimit) -> Propagation {
+        loop {
+            match self.propagate_stack(limit) {
+                Propagation::Done => {}
+                other => return other,
+            }
+
+            // Learned nogoods can only ban more once everything else has settled
+            if !self.enforce_nogoods() {
+                return Propagation::Contradiction;
+            }
+            if self.state.stack.is_empty() {
+                return Propagation::Done;
+            }
+        }
+    }
+
+    fn propagate_stack(&mut self, limit: &mut WorkLimit) -> Propagation {
+        while let Some((cell_idx, pattern_idx)) = self.state.stack.pop() {
+            for d_idx in 0..self.topology.direction_count() {
+                let d = DirectionIndex { base: d_idx };
+
+                if let Some(neighbor_cell) = self.topology.neighbor(cell_idx, d) {
+                    let opp_dir = self.topology.opposite(d);
+
+                    // Grouping references to avoid multiple field lookups
+                    let state = &mut self.state;
+                    let queue = &mut self.to_ban_queue;
+
+                    self.propagator
+                        .for_each_compatible_pattern(pattern_idx, d, |t2| {
+                            let new_count = state.compatible.decrement(neighbor_cell, t2, opp_dir);
+
+                            if new_count == 0 && state.wave.is_candidate(neighbor_cell, t2) {
+                                queue.push((neighbor_cell, t2, Reason::Support(cell_idx)));
+                            }
+                        });
+                }
+            }
+
+            if !self.process_ban_queue() {
+                return Propagation::Contradiction;
+            }
+
+            limit.record_pop();
+            if !self.state.stack.is_empty() && limit.exhausted_during_propagation(self.state.ban_count) {
+                return Propagation::Paused;
+            }
+        }
+        Propagation::Done
+    }
+
+    fn process_ban_queue(&mut self) -> bool {
+        let mut i = 0;
+        while i < self.to_ban_queue.len() {
+            let (c_idx, p_idx, reason) = self.to_ban_queue[i];
+
+            if self.state.wave.is_candidate(c_idx, p_idx) {
+                self.state.ban(c_idx, p_idx, reason);
+
+                if self.state.entropy_tracker.has_no_possible_patterns(c_idx) {
+                    self.to_ban_queue.clear();
+                    self.set_conflict(&[c_idx]);
+                    return false;
+                }
+            }
+            i += 1;
+        }
+
+        self.to_ban_queue.clear();
+        true
+    }
+
+    // Bans the last open pattern of every nogood that has all its other cells set.
+    // Only the nogoods of cells set since the last call can have changed.
+    fn enforce_nogoods(&mut self) -> bool {
+        let mut determined = std::mem::take(&mut self.state.determined);
+        let ids = self.nogoods.affected(&determined, &self.state.observed);
+
+        determined.clear();
+        self.state.determined = determined;
+
+        for id in ids {
+            match self.nogoods.state(id, &self.state.wave, &self.state.observed) {
+                NogoodState::Free => {}
+                NogoodState::Unit(cell_idx, pattern_idx) => {
+                    self.to_ban_queue.push((cell_idx, pattern_idx, Reason::Nogood(id)));
+                }
+                NogoodState::Violated => {
+                    let cells: Vec<CellIndex> = self.nogoods.get(id).iter().map(|&(cell, _)| cell).collect();
+
+                    self.to_ban_queue.clear();
+                    self.set_conflict(&cells);
+                    return false;
+                }
+            }
+        }
+
+        self.process_ban_queue()
+    }
+
+    fn set_conflict(&mut self, cells: &[CellIndex]) {
+        self.conflict.clear();
+        self.conflict.extend_from_slice(cells);
+    }
+
+    fn collapse_cell(&mut self, cell: CellIndex, chosen_t: PatternIndex) {
+        // We use a local buffer to avoid the borrow checker "Boss"
+        let mut local_to_ban = Vec::with_capacity(64);
+
+        // 1. Ask the state's wave which patterns must go
+        self.state
+            .wave
+            .collapse_to_pattern(cell, chosen_t, |p_idx| {
+                local_to_ban.push(p_idx);
+            });
+
+        // 2. Tell the state to ban them one by one
+        for p_idx in local_to_ban {
+            self.state.ban(cell, p_idx, Reason::Decision);
+        }
+    }
+
+    /// Undoes the most recent observation that can still be undone and bans the
+    /// pattern it chose. Returns false when there is none left.
+    pub fn revert(&mut self) -> bool {
+        match self.backtracking {
+            Backtracking::Snapshot => self.revert_to_snapshot(),
+            Backtracking::Trail | Backtracking::Backjump => self.revert_trail(),
+        }
+    }
+
+    fn revert_to_snapshot(&mut self) -> bool {
+        if let Some(s) = self.history.pop() {
+            // 1. Restore Wave
+            self.state.wave.set_data(&s);
+
+            // 2. Reset counts and Entropy
+            self.state.compatible.reset(&self.propagator);
+            self.state.entropy_tracker.reset();
+            self.state.observed.fill(-1);
+
+            // 3. The "Heavy Lifter": Rebuild state from the Wave
+            self.rebuild_state_from_wave();
+
+            // 4. Restore the uncollapsed cell list
+            self.cells_collapsed.reset_from_snapshot(
+                &s.cells_collapsed_indices,
+                &self.state.entropy_tracker,
+                &*self.selector,
+            );
+
+            // 5. Cleanup and re-apply the triggering ban
+            self.to_ban_queue.clear();
+            self.state.stack.clear();
+            self.decision_count = s.decision;
+            self.state.dirty_cells.mark_all_dirty();
+
+            self.state.ban(s.target_cell, s.tried_pattern, Reason::Unknown);
+
+            // 6. The snapshot may predate constraints added mid-generation
+            self.reapply_constraints();
+
+            return true;
+        }
+
+        false
+    }
+
+    fn revert_trail(&mut self) -> bool {
+        let involved = self.explain_conflict();
+        let learned = involved.as_ref().and_then(|involved| self.learn_nogood(involved));
+
+        let decision = match (self.backtracking, involved) {
+            (Backtracking::Backjump, Some(involved)) => match involved.last() {
+                Some(&culprit) => self.state.trail.pop_decisions_to(culprit),
+                None => None,
+            },
+            _ => self.state.trail.pop_decision(),
+        };
+        let decision = match decision {
+            Some(decision) => decision,
+            None => return false,
+        };
+
+        // 1. Bans still waiting on the stack get their counts applied, so that
+        //    every ban on the trail is undone the same way
+        while let Some((cell_idx, pattern_idx)) = self.state.stack.pop() {
+            self.manually_propagate_ban(cell_idx, pattern_idx);
+        }
+        self.to_ban_queue.clear();
+
+        // 2. Undo the bans in reverse order
+        while let Some(entry) = self.state.trail.pop_ban_above(decision.trail_len) {
+            let cell_idx = entry.cell;
+
+            self.state.wave.restore_candidate(cell_idx, entry.pattern);
+            self.state.entropy_tracker.unban_pattern(cell_idx, entry.previous);
+            self.restore_neighbor_counts(cell_idx, entry.pattern);
+
+            self.state.observed[cell_idx] = if self.state.entropy_tracker.pattern_determined(cell_idx) {
+                self.state.wave.find_remaining_pattern(cell_idx)
+            } else {
+                -1
+            };
+            self.state.dirty_cells.mark_dirty(cell_idx);
+            self.state.touched.mark_dirty(cell_idx);
+            self.cells_collapsed
+                .reopen(cell_idx, &self.state.entropy_tracker, &*self.selector);
+        }
+        // Nogoods learned after the decision were never checked against this state
+        self.nogoods.recheck_all();
+
+        // 3. Re-apply the triggering ban, propagated by the next step. When the
+        //    decision is part of the learned nogood, the nogood explains the ban.
+        let reason = match learned {
+            Some(id) if self.nogoods.get(id).last() == Some(&(decision.cell, decision.pattern)) => Reason::Nogood(id),
+            _ => Reason::Unknown,
+        };
+        self.state.ban(decision.cell, decision.pattern, reason);
+        self.reapply_constraints();
+
+        true
+    }
+
+    // The decisions behind the last contradiction, as indices into the trail's
+    // decisions. None when no contradiction was recorded or nothing uses it.
+    fn explain_conflict(&mut self) -> Option<Vec<usize>> {
+        let needed = self.nogoods.is_enabled() || self.backtracking == Backtracking::Backjump;
+        if self.conflict.is_empty() || !needed {
+            return None;
+        }
+
+        let involved = self.nogoods.explain(&self.state.trail, &self.conflict);
+
+        self.conflict.clear();
+        Some(involved)
+    }
+
+    fn learn_nogood(&mut self, involved: &[usize]) -> Option<usize> {
+        if !self.nogoods.is_enabled() {
+            return None;
+        }
+
+        let decisions = self.state.trail.decisions();
+        let assignments = involved
+            .iter()
+            .map(|&k| (decisions[k].cell, decisions[k].pattern))
+            .collect();
+
+        self.nogoods.learn(assignments)
+    }
+
+    /// Discards the output inside the `w` x `h` rectangle at (`x`, `y`) and prepares
+    /// to generate it again while every cell outside of it stays fixed. Call
+    /// [`Solver::step`] or one of the run methods afterwards; `clear()` returns to
+    /// generating the whole output.
+    ///
+    /// Voxel grids with more than one layer are rejected. On `Err` the solver is
+    /// left as it was before the call.
+    pub fn regenerate_region(&mut self, x: usize, y: usize, w: usize, h: usize) -> Result<(), WfcError> {
+        if self.depth > 1 {
+            return Err(WfcError::InvalidDepth { depth: self.depth });
+        }
+
+        let x_end = x.checked_add(w).filter(|&end| end <= self.width);
+        let y_end = y.checked_add(h).filter(|&end| end <= self.height);
+
+        let (x_end, y_end) = match (x_end, y_end) {
+            (Some(x_end), Some(y_end)) => (x_end, y_end),
+            // The first column or row past the edge, on the axis that overflows
+            _ => {
+                let x = if x_end.is_none() { x.max(self.width) } else { x };
+                let y = if y_end.is_none() { y.max(self.height) } else { y };
+
+                return Err(WfcError::CellOutOfRange { x, y });
+            }
+        };
+
+        let region: Vec<CellIndex> = (y..y_end)
+            .flat_map(|cy| (x..x_end).map(move |cx| (cx, cy)))
+            .map(|(cx, cy)| self.cell.get_index(cx as i32, cy as i32))
+            .filter(|&cell_idx| self.is_active(cell_idx))
+            .collect();
+
+        // Kept to roll back to if the region cannot be filled
+        let checkpoint = self.checkpoint();
+        let saved_history = self.history.take();
+
+        // 1. Open the region back up
+        for &cell_idx in &region {
+            self.state.wave.reset_cell(cell_idx);
+        }
+
+        // 2. Counts and entropy are rebuilt from the wave, as in revert()
+        self.state.compatible.reset(&self.propagator);
+        self.state.entropy_tracker.reset();
+        self.state.observed.fill(-1);
+        self.rebuild_state_from_wave();
+
+        self.state.trail.clear();
+        self.nogoods.clear();
+        self.to_ban_queue.clear();
+        self.state.stack.clear();
+        self.decision_count = 0;
+        self.generation_complete = false;
+        self.state.dirty_cells.mark_all_dirty();
+
+        // 3. Patterns no longer supported by the fixed neighbors go first
+        for &cell_idx in &region {
+            for t in 0..self.t_count {
+                let pattern_idx = PatternIndex { base: t };
+
+                if self.state.wave.is_candidate(cell_idx, pattern_idx) && self.is_unsupported(cell_idx, pattern_idx) {
+                    self.state.ban(cell_idx, pattern_idx, Reason::Fixed);
+                }
+            }
+        }
+        self.reapply_constraints();
+
+        let contradiction = region
+            .iter()
+            .any(|&cell_idx| self.state.entropy_tracker.has_no_possible_patterns(cell_idx));
+
+        if contradiction || !self.propagate() {
+            self.restore(checkpoint);
+            self.history = saved_history;
+
+            return Err(WfcError::Contradiction { x, y });
+        }
+
+        // 4. Only the region is left to observe
+        self.cells_collapsed.reset_to(&region, &self.state.entropy_tracker, &*self.selector);
+        self.refresh_observation_queue();
+
+        Ok(())
+    }
+
+    // A pattern is unsupported when some existing neighbor has no compatible pattern left.
+    fn is_unsupported(&self, cell_idx: CellIndex, pattern_idx: PatternIndex) -> bool {
+        (0..self.topology.direction_count()).any(|d_idx| {
+            let d = DirectionIndex { base: d_idx };
+            let has_neighbor = self.topology.neighbor(cell_idx, d).is_some();
+
+            has_neighbor && self.state.compatible.get(cell_idx, pattern_idx, d) == 0
+        })
+    }
+
+    fn rebuild_state_from_wave(&mut self) {
+        let n_cells = self.n_cells;
+        let t_count = self.t_count;
+
+        for i in 0..n_cells {
+            let cell_idx = CellIndex { base: i };
+
+            // We only need to propagate cells that have had patterns removed
+            if self.state.wave.is_fully_undetermined(cell_idx) {
+                continue;
+            }
+
+            for t in 0..t_count {
+                let pattern_idx = PatternIndex { base: t };
+                if !self.state.wave.is_candidate(cell_idx, pattern_idx) {
+                    // If the pattern is banned in the wave,
+                    // update the neighbor counts
+                    self.manually_propagate_ban(cell_idx, pattern_idx);
+                    self.state.entropy_tracker.ban_pattern(cell_idx, pattern_idx);
+                }
+            }
+
+            if self.state.entropy_tracker.pattern_determined(cell_idx) {
+                let p = self.state.wave.find_remaining_pattern(cell_idx);
+                self.state.observed[cell_idx] = p;
+            }
+        }
+
+        self.observe_single_pattern();
+    }
+
+    fn manually_propagate_ban(&mut self, cell_idx: CellIndex, pattern_idx: PatternIndex) {
+        for d_idx in 0..self.topology.direction_count() {
+            let d = DirectionIndex { base: d_idx };
+
+            if let Some(neighbor_cell) = self.topology.neighbor(cell_idx, d) {
+                let opp_dir = self.topology.opposite(d);
+
+                let propagator = &self.propagator;
+                let compatible = &mut self.state.compatible;
+
+                // Update neighbor counts for all patterns supported by the banned pattern
+                propagator.for_each_compatible_pattern(pattern_idx, d, |t2| {
+                    compatible.decrement(neighbor_cell, t2, opp_dir);
+                });
+            }
+        }
+    }
+
+    // Undoes `manually_propagate_ban`.
+    fn restore_neighbor_counts(&mut self, cell_idx: CellIndex, pattern_idx: PatternIndex) {
+        for d_idx in 0..self.topology.direction_count() {
+            let d = DirectionIndex { base: d_idx };
+
+            if let Some(neighbor_cell) = self.topology.neighbor(cell_idx, d) {
+                let opp_dir = self.topology.opposite(d);
+
+                let propagator = &self.propagator;
+                let compatible = &mut self.state.compatible;
+
+                propagator.for_each_compatible_pattern(pattern_idx, d, |t2| {
+                    compatible.increment(neighbor_cell, t2, opp_dir);
+                });
+            }
+        }
+    }
+
+    /// Observes one cell and propagates the result.
+    pub fn step(&mut self) -> IterationResult {
+        let mut never = || false;
+        let mut limit = WorkLimit::new(WorkBudget::UNLIMITED, self.state.ban_count, &mut never);
+
+        self.advance(&mut limit)
+    }
+
+    /// Observes and propagates until `budget` is spent or `deadline_reached` returns
+    /// true, then returns so the caller can yield. Propagation that is cut short is
+    /// resumed by the next call; `result` is `STEP` while there is work left.
+    pub fn step_for<F>(&mut self, budget: WorkBudget, mut deadline_reached: F) -> RunSummary
+    where
+        F: FnMut() -> bool,
+    {
+        let mut summary = RunSummary {
+            result: IterationResult::STEP,
+            steps: 0,
+            reverts: 0,
+            restarts: 0,
+        };
+        let mut limit = WorkLimit::new(budget, self.state.ban_count, &mut deadline_reached);
+
+        while !limit.exhausted(self.state.ban_count) {
+            summary.result = self.advance(&mut limit);
+            summary.steps += 1;
+
+            match summary.result {
+                IterationResult::SUCCESS | IterationResult::FAIL => break,
+                IterationResult::REVERT => summary.reverts += 1,
+                IterationResult::STEP => {}
+            }
+        }
+
+        summary
+    }
+
+    /// True while a `step_for` call stopped in the middle of propagating.
+    pub fn is_propagation_pending(&self) -> bool {
+        !self.state.stack.is_empty()
+    }
+
+    fn advance(&mut self, limit: &mut WorkLimit) -> IterationResult {
+        if self.failed {
+            return IterationResult::FAIL;
+        }
+
+        // Finish propagation left over from a budgeted call or a revert before
+        // looking at entropies again.
+        if self.is_propagation_pending() {
+            match self.propagate_limited(limit) {
+                Propagation::Done => self.refresh_observation_queue(),
+                Propagation::Paused => return IterationResult::STEP,
+                Propagation::Contradiction => return self.revert_or_fail(),
+            }
+        }
+
+        let target = self.find_observe_target();
+
+        match target {
+            None => {
+                self.generation_complete = true;
+                IterationResult::SUCCESS
+            }
+            Some(i) => {
+                if self.state.entropy_tracker.has_no_possible_patterns(i) {
+                    self.set_conflict(&[i]);
+                    return self.revert_or_fail();
+                }
+
+                // Ties between targets are broken by the noise field, so the pattern
+                // choice gets its own independent draw.
+                let pattern_roll = self.rng.next_f64();
+                let context = ChoiceContext {
+                    wave: &self.state.wave,
+                    entropy_tracker: &self.state.entropy_tracker,
+                    propagator: &self.propagator,
+                    topology: &*self.topology,
+                };
+                let chosen_t = self.chooser.choose(i, pattern_roll, &context);
+
+                self.take_snapshot(i, chosen_t);
+                self.collapse_cell(i, chosen_t);
+
+                match self.propagate_limited(limit) {
+                    Propagation::Done => {
+                        self.refresh_observation_queue();
+                        IterationResult::STEP
+                    }
+                    Propagation::Paused => IterationResult::STEP,
+                    Propagation::Contradiction => self.revert_or_fail(),
+                }
+            }
+        }
+    }
+
+    fn revert_or_fail(&mut self) -> IterationResult {
+        if self.revert() {
+            IterationResult::REVERT
+        } else {
+            self.failed = true;
+            IterationResult::FAIL
+        }
+    }
+
+    /// Steps until the output is complete, it fails, or `max_steps` steps have run.
+    /// With `restart_on_fail` a failed attempt is cleared and generation starts over
+    /// within the same step budget.
+    pub fn run(&mut self, max_steps: usize, restart_on_fail: bool) -> RunSummary {
+        let mut summary = RunSummary {
+            result: IterationResult::STEP,
+            steps: 0,
+            reverts: 0,
+            restarts: 0,
+        };
+
+        while summary.steps < max_steps {
+            summary.result = self.step();
+            summary.steps += 1;
+
+            match summary.result {
+                IterationResult::SUCCESS => break,
+                IterationResult::REVERT => summary.reverts += 1,
+                IterationResult::FAIL if restart_on_fail && summary.steps < max_steps => {
+                    self.clear();
+                    summary.restarts += 1;
+                }
+                IterationResult::FAIL => break,
+                IterationResult::STEP => {}
+            }
+        }
+
+        summary
+    }
+
+    /// Runs whole attempts until one succeeds, clearing after each failure.
+    /// Gives up with `FAIL` after `max_attempts` failed attempts.
+    pub fn run_until_complete(&mut self, max_attempts: usize) -> RunSummary {
+        let mut summary = RunSummary {
+            result: IterationResult::FAIL,
+            steps: 0,
+            reverts: 0,
+            restarts: 0,
+        };
+
+        for attempt in 0..max_attempts {
+            if attempt > 0 {
+                self.clear();
+                summary.restarts += 1;
+            }
+
+            loop {
+                summary.result = self.step();
+                summary.steps += 1;
+
+                match summary.result {
+                    IterationResult::SUCCESS => return summary,
+                    IterationResult::FAIL => break,
+                    IterationResult::REVERT => summary.reverts += 1,
+                    IterationResult::STEP => {}
+                }
+            }
+        }
+
+        summary
+    }
+
+    /// Runs attempts until one succeeds or `policy` runs out of them, then
+    /// returns `SUCCESS` or `FAIL`. The first attempt continues from the current
+    /// state. Each later one clears the output and reseeds with a seed derived
+    /// from the solver's seed and the attempt number, so a run is reproducible.
+    /// Every attempt is recorded in [`Solver::attempt_log`]. Fails without
+    /// running when the policy does not pass [`RestartPolicy::check`].
+    pub fn run_with_restarts(&mut self, policy: RestartPolicy) -> Result<RunSummary, WfcError> {
+        policy.check()?;
+
+        let mut summary = RunSummary {
+            result: IterationResult::FAIL,
+            steps: 0,
+            reverts: 0,
+            restarts: 0,
+        };
+        self.attempt_log.clear();
+
+        for attempt in 0..policy.max_attempts() {
+            let mut seed = self.seed;
+            if attempt > 0 {
+                seed = attempt_seed(self.seed, attempt);
+                self.rng = Rng::new(seed);
+                self.clear();
+                summary.restarts += 1;
+            }
+
+            let run = self.run(policy.step_budget(attempt), false);
+            summary.steps += run.steps;
+            summary.reverts += run.reverts;
+
+            self.attempt_log.push(AttemptRecord {
+                seed,
+                result: run.result,
+                steps: run.steps,
+                reverts: run.reverts,
+            });
+
+            if run.result == IterationResult::SUCCESS {
+                summary.result = IterationResult::SUCCESS;
+                break;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// The attempts of the last [`Solver::run_with_restarts`] call, in order.
+    pub fn attempt_log(&self) -> &[AttemptRecord] {
+        &self.attempt_log
+    }
+
+    fn find_observe_target(&self) -> Option<CellIndex> {
+        self.cells_collapsed.next_target()
+    }
+
+    // Rescores the cells banned from since the last refresh.
+    fn refresh_observation_queue(&mut self) {
+        self.cells_collapsed.refresh(&mut self.state.touched, &self.state.entropy_tracker, &*self.selector);
+    }
+
+    pub(crate) fn wave_ptr(&self) -> *const u64 {
+        self.state.wave.as_ptr()
+    }
+    pub(crate) fn observed_ptr(&self) -> *const i32 {
+        self.state.observed.data.as_ptr()
+    }
+    pub(crate) fn entropies_ptr(&self) -> *const f64 {
+        self.state.entropy_tracker.entropies_ptr()
+    }
+
+    pub fn clear(&mut self) {
+        self.generation_complete = false;
+        self.failed = false;
+        self.state.wave.fill(1);
+        self.state.observed.fill(-1);
+        self.history.clear();
+        self.decision_count = 0;
+        self.state.trail.clear();
+        self.nogoods.clear();
+        self.conflict.clear();
+        self.state.dirty_cells.clear();
+        self.state.touched.clear();
+        self.state.compatible.reset(&self.propagator);
+        self.state.entropy_tracker.reset();
+        self.state.stack.clear();
+        self.state.determined.clear();
+        self.selector.reseed(&mut self.rng);
+        self.cells_collapsed.reset(&self.state.entropy_tracker, &*self.selector);
+        self.observe_single_pattern();
+        self.apply_persistent_bans();
+    }
+
+    // With a single pattern nothing is ever banned, so no ban marks the cells as
+    // observed. They are determined from the start instead.
+    fn observe_single_pattern(&mut self) {
+        if self.t_count != 1 {
+            return;
+        }
+
+        for i in 0..self.n_cells {
+            let cell_idx = CellIndex { base: i };
+
+            if self.is_active(cell_idx) && self.state.entropy_tracker.pattern_determined(cell_idx) {
+                self.state.observed[cell_idx] = 0;
+            }
+        }
+    }
+
+    /// Changes how the west and east edges behave and clears the output.
+    pub fn set_boundary_x(&mut self, mode: BoundaryMode) -> Result<(), WfcError> {
+        self.check_boundary_mode(mode)?;
+        self.boundary.x = mode;
+        self.rebuild_topology();
+        Ok(())
+    }
+
+    /// Changes how the north and south edges behave and clears the output.
+    pub fn set_boundary_y(&mut self, mode: BoundaryMode) -> Result<(), WfcError> {
+        self.check_boundary_mode(mode)?;
+        if self.shape == GridShape::Hex && mode == BoundaryMode::Periodic && self.height % 2 == 1 {
+            return Err(WfcError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        self.boundary.y = mode;
+        self.rebuild_topology();
+        Ok(())
+    }
+
+    /// Changes how the first and last z layers of a voxel grid behave and clears the output.
+    pub fn set_boundary_z(&mut self, mode: BoundaryMode) -> Result<(), WfcError> {
+        self.check_boundary_mode(mode)?;
+        self.boundary.z = mode;
+        self.rebuild_topology();
+        Ok(())
+    }
+
+    /// Marks the patterns allowed along `BoundaryMode::EdgeCompatible` edges and clears the output.
+    pub fn set_edge_patterns(&mut self, patterns: &[PatternIndex]) -> Result<(), WfcError> {
+        for &p in patterns {
+            self.check_pattern(p)?;
+        }
+
+        self.boundary.edge_patterns.fill(false);
+        for &p in patterns {
+            self.boundary.edge_patterns[p.base] = true;
+        }
+        self.rebuild_topology();
+        Ok(())
+    }
+
+    /// Replaces the pattern requirement along `side` and clears the output.
+    pub fn set_side(&mut self, side: Direction, spec: SideSpec) -> Result<(), WfcError> {
+        for &p in spec.patterns() {
+            self.check_pattern(p)?;
+        }
+
+        self.boundary.sides[side as usize] = spec;
+        self.rebuild_topology();
+        Ok(())
+    }
+
+    fn check_boundary_mode(&self, mode: BoundaryMode) -> Result<(), WfcError> {
+        match mode {
+            BoundaryMode::Fixed(pattern) => self.check_pattern(pattern),
+            _ => Ok(()),
+        }
+    }
+
+    /// Limits generation to the cells where `active` is true and clears the output.
+    /// See [`WfcConfigBuilder::mask`](crate::WfcConfigBuilder::mask).
+    pub fn set_mask(&mut self, active: Vec<bool>, mode: MaskMode) -> Result<(), WfcError> {
+        let mask = Mask { active, mode };
+
+        mask.check(self.width, self.height, self.depth)?;
+        if let MaskMode::Void(pattern) = mode {
+            self.check_pattern(pattern)?;
+        }
+
+        self.replace_mask(Some(mask));
+        Ok(())
+    }
+
+    /// Brings every cell back into generation and clears the output.
+    pub fn clear_mask(&mut self) {
+        self.replace_mask(None);
+    }
+
+    fn replace_mask(&mut self, mask: Option<Mask>) {
+        if let Some(mask) = &mask {
+            self.constraints.retain(|&(cell_idx, _)| mask.is_active(cell_idx));
+        }
+
+        self.cells_collapsed = CellCollapsedCollection::new(active_cells(mask.as_ref(), self.n_cells), self.n_cells);
+        self.mask = mask;
+        self.rebuild_topology();
+    }
+
+    fn rebuild_topology(&mut self) {
+        let topology: Box<dyn Topology> = match &self.graph {
+            Some(graph) => Box::new(graph.clone()),
+            None => build_topology(self.shape, self.width, self.height, self.depth, &self.boundary),
+        };
+        let (topology, void_bans) = apply_mask(topology, self.mask.as_ref(), &self.propagator, self.t_count);
+
+        self.topology = topology;
+        self.void_bans = void_bans;
+        self.boundary_bans = self.collect_edge_bans();
+        self.clear();
+    }
+
+    // Bans from the boundary of a grid and from the void around masked cells.
+    fn collect_edge_bans(&self) -> Constraints {
+        let (width, height, depth) = (self.width, self.height, self.depth);
+        // Graphs have no edges to constrain.
+        let mut bans = match self.graph {
+            Some(_) => Constraints::new(),
+            None => self
+                .boundary
+                .collect_bans(&*self.topology, &self.cell, width, height, depth, &self.propagator),
+        };
+
+        if let Some(mask) = &self.mask {
+            bans.retain(|&(cell_idx, _)| mask.is_active(cell_idx));
+        }
+        bans.extend(&self.void_bans);
+
+        bans
+    }
+
+    // Unsupported patterns, boundary and user constraints on a freshly reset wave.
+    fn apply_persistent_bans(&mut self) {
+        let unsupported = self.ban_unsupported();
+        if !unsupported && self.constraints.is_empty() && self.boundary_bans.is_empty() {
+            return;
+        }
+
+        self.reapply_constraints();
+
+        let emptied = (0..self.n_cells)
+            .map(|i| CellIndex { base: i })
+            .any(|cell_idx| self.state.entropy_tracker.has_no_possible_patterns(cell_idx));
+
+        self.failed = emptied || !self.propagate();
+        self.refresh_observation_queue();
+    }
+
+    // A pattern with no partner in some direction can only sit where that
+    // direction leads out of the grid. Returns whether anything was banned.
+    fn ban_unsupported(&mut self) -> bool {
+        let d_count = self.topology.direction_count();
+        let lonely = (0..self.t_count).any(|t| {
+            (0..d_count).any(|d| {
+                self.propagator
+                    .get_compatible_count(PatternIndex { base: t }, DirectionIndex { base: d })
+                    == 0
+            })
+        });
+        if !lonely {
+            return false;
+        }
+
+        let mut banned = false;
+        for i in 0..self.n_cells {
+            let cell_idx = CellIndex { base: i };
+            if !self.is_active(cell_idx) {
+                continue;
+            }
+
+            for t in 0..self.t_count {
+                let pattern_idx = PatternIndex { base: t };
+
+                if self.state.wave.is_candidate(cell_idx, pattern_idx) && self.is_unsupported(cell_idx, pattern_idx) {
+                    self.state.ban(cell_idx, pattern_idx, Reason::Fixed);
+                    banned = true;
+                }
+            }
+        }
+
+        banned
+    }
+
+    /// Forces the cell at (`x`, `y`) to `pattern` and propagates immediately.
+    pub fn set_cell(&mut self, x: usize, y: usize, pattern: PatternIndex) -> Result<(), WfcError> {
+        self.set_cell_3d(x, y, 0, pattern)
+    }
+
+    /// [`Solver::set_cell`] on layer `z` of a voxel grid.
+    pub fn set_cell_3d(&mut self, x: usize, y: usize, z: usize, pattern: PatternIndex) -> Result<(), WfcError> {
+        self.restrict_cell_3d(x, y, z, &[pattern])
+    }
+
+    /// Forbids `pattern` at (`x`, `y`) and propagates immediately.
+    pub fn ban_at(&mut self, x: usize, y: usize, pattern: PatternIndex) -> Result<(), WfcError> {
+        self.ban_at_3d(x, y, 0, pattern)
+    }
+
+    /// [`Solver::ban_at`] on layer `z` of a voxel grid.
+    pub fn ban_at_3d(&mut self, x: usize, y: usize, z: usize, pattern: PatternIndex) -> Result<(), WfcError> {
+        let cell_idx = self.cell_at(x, y, z)?;
+        self.check_pattern(pattern)?;
+
+        self.apply_constraint(x, y, &[(cell_idx, pattern)])
+    }
+
+    /// Limits the cell at (`x`, `y`) to the `allowed` patterns and propagates immediately.
+    ///
+    /// Constraints persist through `clear()` and `revert()`. A constraint that causes
+    /// a contradiction is discarded and leaves the solver as it was before the call.
+    pub fn restrict_cell(&mut self, x: usize, y: usize, allowed: &[PatternIndex]) -> Result<(), WfcError> {
+        self.restrict_cell_3d(x, y, 0, allowed)
+    }
+
+    /// [`Solver::restrict_cell`] on layer `z` of a voxel grid.
+    pub fn restrict_cell_3d(&mut self, x: usize, y: usize, z: usize, allowed: &[PatternIndex]) -> Result<(), WfcError> {
+        let cell_idx = self.cell_at(x, y, z)?;
+        for &p in allowed {
+            self.check_pattern(p)?;
+        }
+
+        let bans: Vec<(CellIndex, PatternIndex)> = (0..self.t_count)
+            .map(|t| PatternIndex { base: t })
+            .filter(|p| !allowed.contains(p))
+            .map(|p| (cell_idx, p))
+            .collect();
+
+        self.apply_constraint(x, y, &bans)
+    }
+
+    /// Removes every constraint and clears the output.
+    pub fn clear_constraints(&mut self) {
+        self.constraints.clear();
+        self.clear();
+    }
+
+    fn apply_constraint(&mut self, x: usize, y: usize, bans: &[(CellIndex, PatternIndex)]) -> Result<(), WfcError> {
+        let previous_len = self.constraints.len();
+        let checkpoint = self.checkpoint();
+        let mut contradiction = false;
+
+        for &(cell_idx, pattern_idx) in bans {
+            self.constraints.push(cell_idx, pattern_idx);
+            self.state.ban(cell_idx, pattern_idx, Reason::Fixed);
+            contradiction |= self.state.entropy_tracker.has_no_possible_patterns(cell_idx);
+        }
+
+        if contradiction || !self.propagate() {
+            self.constraints.truncate(previous_len);
+            self.restore(checkpoint);
+
+            return Err(WfcError::Contradiction { x, y });
+        }
+
+        self.refresh_observation_queue();
+        Ok(())
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            state: self.state.clone(),
+            cells_collapsed: self.cells_collapsed.clone(),
+            nogoods: self.nogoods.clone(),
+            conflict: self.conflict.clone(),
+            to_ban_queue: self.to_ban_queue.clone(),
+            decision_count: self.decision_count,
+            generation_complete: self.generation_complete,
+            failed: self.failed,
+        }
+    }
+
+    // Puts back a checkpoint. Every cell is reported as changed, since the
+    // rolled back bans were already seen by `take_changes`.
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.state = checkpoint.state;
+        self.cells_collapsed = checkpoint.cells_collapsed;
+        self.nogoods = checkpoint.nogoods;
+        self.conflict = checkpoint.conflict;
+        self.to_ban_queue = checkpoint.to_ban_queue;
+        self.decision_count = checkpoint.decision_count;
+        self.generation_complete = checkpoint.generation_complete;
+        self.failed = checkpoint.failed;
+        self.state.dirty_cells.mark_all_dirty();
+    }
+
+    fn reapply_constraints(&mut self) {
+        let bans = self.boundary_bans.bans().iter().chain(self.constraints.bans());
+
+        for &(cell_idx, pattern_idx) in bans {
+            self.state.ban(cell_idx, pattern_idx, Reason::Fixed);
+        }
+    }
+
+    fn cell_at(&self, x: usize, y: usize, z: usize) -> Result<CellIndex, WfcError> {
+        if x >= self.width || y >= self.height {
+            return Err(WfcError::CellOutOfRange { x, y });
+        }
+        if z >= self.depth {
+            return Err(WfcError::LayerOutOfRange { z, depth: self.depth });
+        }
+
+        let cell_idx = self.cell.get_index_3d(x as i32, y as i32, z as i32);
+        if !self.is_active(cell_idx) {
+            return Err(WfcError::CellOutOfRange { x, y });
+        }
+
+        Ok(cell_idx)
+    }
+
+    #[inline(always)]
+    fn is_active(&self, cell_idx: CellIndex) -> bool {
+        match &self.mask {
+            Some(mask) => mask.is_active(cell_idx),
+            None => true,
+        }
+    }
+
+    fn check_pattern(&self, pattern: PatternIndex) -> Result<(), WfcError> {
+        if pattern.base >= self.t_count {
+            return Err(WfcError::PatternOutOfRange {
+                pattern,
+                t_count: self.t_count,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Restarts the random sequence as if the solver had been built with `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self.selector.reseed(&mut self.rng);
+        self.cells_collapsed.rescore(&self.state.entropy_tracker, &*self.selector);
+    }
+
+    /// The full PRNG state. Restoring it with [`Solver::set_rng_state`] replays the
+    /// same random decisions from this point on.
+    pub fn rng_state(&self) -> u64 {
+        self.rng.state()
+    }
+
+    pub fn set_rng_state(&mut self, state: u64) {
+        self.rng.set_state(state);
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.t_count
+    }
+
+    /// The pattern the cell at (`x`, `y`) collapsed to, if it has been determined.
+    /// On a voxel grid this reads the first layer.
+    pub fn observed_at(&self, x: usize, y: usize) -> Option<PatternIndex> {
+        self.observed_at_3d(x, y, 0)
+    }
+
+    /// The pattern the cell at (`x`, `y`, `z`) collapsed to, if it has been determined.
+    pub fn observed_at_3d(&self, x: usize, y: usize, z: usize) -> Option<PatternIndex> {
+        if x >= self.width || y >= self.height || z >= self.depth {
+            return None;
+        }
+
+        let cell_idx = self.cell.get_index_3d(x as i32, y as i32, z as i32);
+        let observed = self.state.observed[cell_idx];
+
+        if observed < 0 {
+            None
+        } else {
+            Some(PatternIndex { base: observed as usize })
+        }
+    }
+
+    /// Observed patterns for every cell in row-major order.
+    pub fn observed_patterns(&self) -> Vec<Option<PatternIndex>> {
+        self.state
+            .observed
+            .data
+            .iter()
+            .map(|&p| if p < 0 { None } else { Some(PatternIndex { base: p as usize }) })
+            .collect()
+    }
+
+    /// Whether `pattern` is still possible at (`x`, `y`).
+    pub fn is_candidate_at(&self, x: usize, y: usize, pattern: PatternIndex) -> bool {
+        self.is_candidate_at_3d(x, y, 0, pattern)
+    }
+
+    /// [`Solver::is_candidate_at`] on layer `z` of a voxel grid.
+    pub fn is_candidate_at_3d(&self, x: usize, y: usize, z: usize, pattern: PatternIndex) -> bool {
+        if x >= self.width || y >= self.height || z >= self.depth || pattern.base >= self.t_count {
+            return false;
+        }
+
+        let cell_idx = self.cell.get_index_3d(x as i32, y as i32, z as i32);
+
+        self.state.wave.is_candidate(cell_idx, pattern)
+    }
+
+    /// Changes how `Backtracking::Snapshot` schedules and thins its snapshots.
+    /// `max_bytes` caps their total size, 0 for no cap. Snapshots already taken
+    /// are thinned to fit right away.
+    pub fn set_snapshot_policy(&mut self, policy: SnapshotPolicy, max_bytes: usize) {
+        self.history.set_policy(policy, max_bytes);
+    }
+
+    /// Every `interval`th snapshot taken from now on is stored in full, and the
+    /// ones between only as the words changed since the snapshot before. 1
+    /// stores every snapshot in full.
+    pub fn set_snapshot_keyframe_interval(&mut self, interval: usize) {
+        self.history.set_keyframe_interval(interval);
+    }
+
+    /// The observations, counted from 0, that `Backtracking::Snapshot` can still
+    /// revert to, oldest first.
+    pub fn snapshot_decisions(&self) -> Vec<usize> {
+        self.history.decisions()
+    }
+
+    /// Nogoods learned since generation last started over.
+    pub fn learned_nogood_count(&self) -> usize {
+        self.nogoods.len()
+    }
+
+    pub fn filled_percent(&self) -> f64 {
+        (self.cells_collapsed.collapsed_count() as f64) / (self.cells_collapsed.active_count() as f64)
+    }
+
+    /// Indices of the cells changed since the last call, in row-major order.
+    pub fn take_changes(&mut self) -> Vec<i32> {
+        self.state.dirty_cells.flush_to_js()
+    }
+
+    pub fn is_generation_complete(&self) -> bool {
+        self.generation_complete
+    }
+
+    pub fn filled_count(&self) -> usize {
+        self.cells_collapsed.collapsed_count()
+    }
+
+    /// Cells taking part in generation, i.e. not masked out.
+    pub fn total_cells(&self) -> usize {
+        self.cells_collapsed.active_count()
+    }
+
+    /// Sum of the buffers owned by the solver: wave, compatible counts,
+    /// entropy tracking and the snapshot history.
+    pub fn memory_usage_bytes(&self) -> usize {
+        let queue_bytes = self.to_ban_queue.capacity() * std::mem::size_of::<(CellIndex, PatternIndex, Reason)>();
+
+        self.state.memory_usage_bytes()
+            + self.cells_collapsed.memory_usage_bytes()
+            + self.constraints.memory_usage_bytes()
+            + self.boundary_bans.memory_usage_bytes()
+            + self.void_bans.memory_usage_bytes()
+            + self.nogoods.memory_usage_bytes()
+            + self.history.memory_usage_bytes()
+            + queue_bytes
+    }
+}
@@ -1,41 +1,58 @@
-use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::cell::{Cell, CellIndex};
 use crate::wfc_model::cell_collection::CellCollection;
+use crate::wfc_model::rng::Rng;
 
 #[derive(Clone)]
 pub struct SpatialPriority {
     data: CellCollection<f64>,
+
+    // Per-cell tie-breaking noise, redrawn from the solver's PRNG for each attempt.
+    // Kept apart from the bias so that it only orders cells with equal scores.
+    noise: CellCollection<f64>,
 }
 
 impl SpatialPriority {
-    pub fn new(
-        width: usize,
-        height: usize,
-        bias: f64,
-        start_x: f64,
-        start_y: f64,
-    ) -> Self {
-        let n_cells = width * height;
+    pub fn new(size: [usize; 3], bias: f64, start: [f64; 3]) -> Self {
+        let [width, height, depth] = size;
+        let n_cells = width * height * depth;
+        let cell = Cell::new(width, height);
         let mut data = CellCollection::new_with_value(n_cells, 0.0);
         let max_x = (width - 1) as f64;
         let max_y = (height - 1) as f64;
-        let cx = max_x * start_x;
-        let cy = max_y * start_y;
+        let max_z = (depth - 1) as f64;
+        let cx = max_x * start[0];
+        let cy = max_y * start[1];
+        let cz = max_z * start[2];
 
         for i in 0..n_cells {
             let cell_idx = CellIndex { base: i };
-            let coords = cell_idx.to_coords(width);
+            let coords = cell.get_coords_3d(cell_idx);
             let x = coords.0 as f64;
             let y = coords.1 as f64;
-            let dist_sq = (x - cx).powi(2) + (y - cy).powi(2);
+            let z = coords.2 as f64;
+            let dist_sq = (x - cx).powi(2) + (y - cy).powi(2) + (z - cz).powi(2);
 
             data[cell_idx] = dist_sq.sqrt() * bias;
         }
 
-        Self { data }
+        let noise = CellCollection::new_with_value(n_cells, 0.0);
+
+        Self { data, noise }
+    }
+
+    pub fn reseed_noise(&mut self, rng: &mut Rng) {
+        for value in self.noise.data.iter_mut() {
+            *value = rng.next_f64();
+        }
     }
 
     #[inline(always)]
     pub fn get_bias(&self, cell: CellIndex) -> f64 {
         self.data[cell]
     }
+
+    #[inline(always)]
+    pub fn get_noise(&self, cell: CellIndex) -> f64 {
+        self.noise[cell]
+    }
 }
\ No newline at end of file
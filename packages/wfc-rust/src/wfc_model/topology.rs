@@ -0,0 +1,125 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::wfc_model::boundary::Boundary;
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::direction::{DirectionIndex, GridDirection, DIRECTIONS};
+use crate::wfc_model::grid_topology::{GridTopology, MOORE_DIRECTIONS};
+use crate::wfc_model::hex_topology::{HexTopology, HEX_DIRECTIONS};
+use crate::wfc_model::voxel_topology::{VoxelTopology, VOXEL_DIRECTIONS};
+
+/// An axis of the output, used to pick its boundary mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// The shape of the output: which cells exist and which cell lies in each
+/// direction of another. Propagation only goes through this trait, so a new
+/// grid shape only needs a new implementation.
+pub trait Topology {
+    fn cell_count(&self) -> usize;
+
+    fn direction_count(&self) -> usize;
+
+    /// The cell in `direction` of `cell`, or `None` past an open edge.
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex>;
+
+    /// Must be an involution, so that a neighbor's neighbor in the opposite
+    /// direction is the cell itself.
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex;
+
+    /// For layouts stored as a `width * height * depth` box: the axis a step in
+    /// `direction` leaves the box along, if it leaves at all. Boundary modes
+    /// only apply where this returns an axis.
+    fn exit_axis(&self, _cell: CellIndex, _direction: DirectionIndex) -> Option<Axis> {
+        None
+    }
+}
+
+/// The built-in cell layouts. All of them store cells row-major in a
+/// `width * height * depth` box, so `(x, y)` coordinates work for each.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GridShape {
+    /// Square cells with 4 neighbors, indexed by [`Direction`].
+    Square,
+
+    /// Square cells with 8 neighbors including the diagonals, indexed by
+    /// [`MooreDirection`].
+    ///
+    /// [`MooreDirection`]: crate::MooreDirection
+    Moore,
+
+    /// Pointy-top hexagons in odd-r offset layout, indexed by [`HexDirection`].
+    ///
+    /// [`HexDirection`]: crate::HexDirection
+    Hex,
+
+    /// Cubes with 6 face neighbors, indexed by [`VoxelDirection`]. The only
+    /// shape that allows a depth above 1.
+    ///
+    /// [`VoxelDirection`]: crate::VoxelDirection
+    Voxel,
+}
+
+impl GridShape {
+    pub fn direction_count(self) -> usize {
+        match self {
+            GridShape::Square => DIRECTIONS.len(),
+            GridShape::Moore => MOORE_DIRECTIONS.len(),
+            GridShape::Hex => HEX_DIRECTIONS.len(),
+            GridShape::Voxel => VOXEL_DIRECTIONS.len(),
+        }
+    }
+
+    /// The direction pointing back along `direction`.
+    pub fn opposite(self, direction: DirectionIndex) -> DirectionIndex {
+        let d = direction.base;
+
+        match self {
+            GridShape::Square => DIRECTIONS[d].opposite().into(),
+            GridShape::Moore => MOORE_DIRECTIONS[d].opposite().into(),
+            GridShape::Hex => HEX_DIRECTIONS[d].opposite().into(),
+            GridShape::Voxel => VOXEL_DIRECTIONS[d].opposite().into(),
+        }
+    }
+}
+
+pub fn build_topology(
+    shape: GridShape,
+    width: usize,
+    height: usize,
+    depth: usize,
+    boundary: &Boundary,
+) -> Box<dyn Topology> {
+    let (periodic_x, periodic_y) = (boundary.periodic_x(), boundary.periodic_y());
+
+    match shape {
+        GridShape::Square => Box::new(GridTopology::new(width, height, periodic_x, periodic_y, false)),
+        GridShape::Moore => Box::new(GridTopology::new(width, height, periodic_x, periodic_y, true)),
+        GridShape::Hex => Box::new(HexTopology::new(width, height, periodic_x, periodic_y)),
+        GridShape::Voxel => Box::new(VoxelTopology::new(
+            width,
+            height,
+            depth,
+            [periodic_x, periodic_y, boundary.periodic_z()],
+        )),
+    }
+}
+
+/// Wraps `v` into `0..size` on a periodic axis, or checks that it is inside.
+#[inline(always)]
+pub fn wrap_axis(v: i32, size: usize, periodic: bool) -> Option<i32> {
+    let size = size as i32;
+
+    if periodic {
+        Some((v % size + size) % size)
+    } else if v >= 0 && v < size {
+        Some(v)
+    } else {
+        None
+    }
+}
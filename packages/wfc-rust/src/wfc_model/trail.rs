@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::wfc_model::cell::CellIndex;
+use crate::wfc_model::entropy_tracker::CellWeights;
+use crate::wfc_model::pattern_collection::PatternIndex;
+
+/// How the solver undoes an observation that led to a contradiction.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backtracking {
+    /// Saves the wave as its `SnapshotPolicy` schedules, mostly as deltas between
+    /// keyframes, and rebuilds the counts and entropies from it on revert.
+    Snapshot,
+
+    /// Records every ban and undoes them in reverse order back to the failed
+    /// observation. Each of the last `max_snapshots` observations can be undone.
+    Trail,
+
+    /// Like `Trail`, but jumps straight back to the most recent observation a
+    /// contradiction depends on, undoing the later ones that had no part in it.
+    /// Fails as soon as a contradiction depends on no observation that can
+    /// still be undone.
+    Backjump,
+}
+
+impl Backtracking {
+    /// Whether bans are recorded on a `Trail`.
+    pub fn uses_trail(self) -> bool {
+        self != Backtracking::Snapshot
+    }
+}
+
+/// Why a pattern was banned, for tracing a contradiction back to the
+/// decisions behind it.
+#[derive(Clone, Copy)]
+pub enum Reason {
+    /// Collapsing the cell at a decision.
+    Decision,
+
+    /// A constraint or boundary, which holds whatever the decisions.
+    Fixed,
+
+    /// The neighbor at this cell no longer supports the pattern.
+    Support(CellIndex),
+
+    /// Every other cell of this learned nogood is set.
+    Nogood(usize),
+
+    /// Assumed to depend on every decision made before it.
+    Unknown,
+}
+
+#[derive(Clone, Copy)]
+pub struct TrailEntry {
+    pub cell: CellIndex,
+    pub pattern: PatternIndex,
+    pub reason: Reason,
+
+    // The cell's entropy sums before the ban, restored as they were so that
+    // undoing does not accumulate rounding errors.
+    pub previous: CellWeights,
+}
+
+/// An observation that can be undone.
+#[derive(Clone, Copy)]
+pub struct Decision {
+    pub cell: CellIndex,
+    pub pattern: PatternIndex,
+
+    // Length of the trail before the cell was collapsed, counting the bans
+    // already forgotten.
+    pub trail_len: usize,
+}
+
+/// The bans made since the oldest decision that can still be undone.
+#[derive(Clone, Default)]
+pub struct Trail {
+    bans: VecDeque<TrailEntry>,
+    decisions: VecDeque<Decision>,
+
+    // Bans forgotten along with the decisions they belonged to.
+    forgotten: usize,
+}
+
+impl Trail {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bans made before the first decision can never be undone, so they are
+    /// not kept.
+    #[inline(always)]
+    pub fn record(&mut self, cell: CellIndex, pattern: PatternIndex, reason: Reason, previous: CellWeights) {
+        if self.decisions.is_empty() {
+            return;
+        }
+
+        self.bans.push_back(TrailEntry {
+            cell,
+            pattern,
+            reason,
+            previous,
+        });
+    }
+
+    /// Opens a new decision level, forgetting the oldest one and its bans once
+    /// `max_decisions` are kept.
+    pub fn push_decision(&mut self, cell: CellIndex, pattern: PatternIndex, max_decisions: usize) {
+        if self.decisions.len() >= max_decisions {
+            self.decisions.pop_front();
+
+            let keep_from = self.decisions.front().map_or(self.trail_len(), |d| d.trail_len);
+            self.bans.drain(..keep_from - self.forgotten);
+            self.forgotten = keep_from;
+        }
+
+        self.decisions.push_back(Decision {
+            cell,
+            pattern,
+            trail_len: self.trail_len(),
+        });
+    }
+
+    /// The number of bans recorded so far, including the forgotten ones.
+    pub fn trail_len(&self) -> usize {
+        self.forgotten + self.bans.len()
+    }
+
+    /// The `i`th recorded ban, which must not be forgotten yet.
+    pub fn ban(&self, i: usize) -> &TrailEntry {
+        &self.bans[i - self.forgotten]
+    }
+
+    /// The decisions that can still be undone, oldest first.
+    pub fn decisions(&self) -> &VecDeque<Decision> {
+        &self.decisions
+    }
+
+    pub fn pop_decision(&mut self) -> Option<Decision> {
+        self.decisions.pop_back()
+    }
+
+    /// Forgets every decision after the `k`th and returns that one.
+    pub fn pop_decisions_to(&mut self, k: usize) -> Option<Decision> {
+        self.decisions.truncate(k + 1);
+        self.decisions.pop_back()
+    }
+
+    /// The most recent ban, as long as more than `trail_len` are recorded.
+    pub fn pop_ban_above(&mut self, trail_len: usize) -> Option<TrailEntry> {
+        if self.trail_len() > trail_len {
+            self.bans.pop_back()
+        } else {
+            None
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bans.clear();
+        self.decisions.clear();
+        self.forgotten = 0;
+    }
+
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.bans.capacity() * std::mem::size_of::<TrailEntry>()
+            + self.decisions.capacity() * std::mem::size_of::<Decision>()
+    }
+}
@@ -0,0 +1,98 @@
+use crate::wfc_model::cell::{Cell, CellIndex};
+use crate::wfc_model::direction::{DirectionIndex, GridDirection};
+use crate::wfc_model::topology::{wrap_axis, Axis, Topology};
+
+/// The six face neighbors of a cube. Opposite directions are three apart.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum VoxelDirection {
+    NegX = 0,
+    NegY = 1,
+    NegZ = 2,
+    PosX = 3,
+    PosY = 4,
+    PosZ = 5,
+}
+
+pub const VOXEL_DIRECTIONS: [VoxelDirection; 6] = [
+    VoxelDirection::NegX,
+    VoxelDirection::NegY,
+    VoxelDirection::NegZ,
+    VoxelDirection::PosX,
+    VoxelDirection::PosY,
+    VoxelDirection::PosZ,
+];
+
+// (dx, dy, dz), indexed by `VoxelDirection`.
+const OFFSETS: [(i32, i32, i32); 6] = [(-1, 0, 0), (0, -1, 0), (0, 0, -1), (1, 0, 0), (0, 1, 0), (0, 0, 1)];
+
+impl GridDirection for VoxelDirection {
+    fn opposite(self) -> Self {
+        VOXEL_DIRECTIONS[(self as usize + 3) % 6]
+    }
+}
+
+impl From<VoxelDirection> for DirectionIndex {
+    #[inline(always)]
+    fn from(direction: VoxelDirection) -> Self {
+        Self {
+            base: direction as usize,
+        }
+    }
+}
+
+/// A `width * height * depth` box of cubes, stored layer by layer along z.
+pub struct VoxelTopology {
+    size: [usize; 3],
+    periodic: [bool; 3],
+    cell: Cell,
+}
+
+impl VoxelTopology {
+    pub fn new(width: usize, height: usize, depth: usize, periodic: [bool; 3]) -> Self {
+        Self {
+            size: [width, height, depth],
+            periodic,
+            cell: Cell::new(width, height),
+        }
+    }
+
+    #[inline(always)]
+    fn step(&self, cell: CellIndex, direction: DirectionIndex) -> Result<CellIndex, Axis> {
+        let (x, y, z) = self.cell.get_coords_3d(cell);
+        let (dx, dy, dz) = OFFSETS[direction.base];
+        let [w, h, d] = self.size;
+        let [px, py, pz] = self.periodic;
+
+        let nx = wrap_axis(x + dx, w, px).ok_or(Axis::X)?;
+        let ny = wrap_axis(y + dy, h, py).ok_or(Axis::Y)?;
+        let nz = wrap_axis(z + dz, d, pz).ok_or(Axis::Z)?;
+
+        Ok(self.cell.get_index_3d(nx, ny, nz))
+    }
+}
+
+impl Topology for VoxelTopology {
+    fn cell_count(&self) -> usize {
+        self.size.iter().product()
+    }
+
+    fn direction_count(&self) -> usize {
+        VOXEL_DIRECTIONS.len()
+    }
+
+    #[inline(always)]
+    fn neighbor(&self, cell: CellIndex, direction: DirectionIndex) -> Option<CellIndex> {
+        self.step(cell, direction).ok()
+    }
+
+    #[inline(always)]
+    fn opposite(&self, direction: DirectionIndex) -> DirectionIndex {
+        DirectionIndex {
+            base: (direction.base + 3) % 6,
+        }
+    }
+
+    fn exit_axis(&self, cell: CellIndex, direction: DirectionIndex) -> Option<Axis> {
+        self.step(cell, direction).err()
+    }
+}
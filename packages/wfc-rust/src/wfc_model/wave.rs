@@ -1,7 +1,8 @@
 use crate::wfc_model::cell::CellIndex;
 use crate::wfc_model::entropy_tracker::EntropyTracker;
+use crate::wfc_model::pattern_bitset::PatternBitSet;
 use crate::wfc_model::pattern_collection::PatternIndex;
-use crate::wfc_model::WaveSnapshot;
+use crate::wfc_model::snapshot_history::WaveSnapshot;
 
 #[derive(Clone)]
 pub struct Wave {
@@ -18,7 +19,7 @@ pub struct WaveIndex {
 
 impl Wave {
     pub fn new(n_cells: usize, t_count: usize) -> Self {
-        let words_per_cell = (t_count + 63) / 64;
+        let words_per_cell = t_count.div_ceil(64);
         let data = vec![0; n_cells * words_per_cell];
 
         let mut wave = Self {
@@ -59,10 +60,16 @@ impl Wave {
     }
 
     #[inline(always)]
-    pub fn as_ptr(&self) -> *const u64 {
-        let ptr = self.data.as_ptr();
+    pub fn restore_candidate(&mut self, cell: CellIndex, pattern: PatternIndex) {
+        let word_idx = self.get_index(cell, pattern).base;
+        let bit = pattern.base & 63;
 
-        ptr
+        self.data[word_idx] |= 1u64 << bit;
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *const u64 {
+        self.data.as_ptr()
     }
 
     pub fn fill(&mut self, value: u8) {
@@ -85,10 +92,27 @@ impl Wave {
         }
     }
 
+    /// Makes every pattern possible again at `cell`.
+    pub fn reset_cell(&mut self, cell: CellIndex) {
+        let start = cell.base * self.words_per_cell;
+        let end = start + self.words_per_cell;
+
+        self.data[start..end].fill(u64::MAX);
+
+        let remainder = self.t_count % 64;
+        if remainder != 0 {
+            self.data[end - 1] &= (1u64 << remainder) - 1;
+        }
+    }
+
     pub fn clone_data(&self) -> Vec<u64> {
         self.data.clone()
     }
 
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<u64>()
+    }
+
     pub fn set_data(&mut self, s: &WaveSnapshot) {
         self.data.copy_from_slice(&s.wave_data);
     }
@@ -137,6 +161,24 @@ impl Wave {
         PatternIndex { base: 0 }
     }
 
+    /// The patterns still possible in `cell`, in index order.
+    pub fn candidates(&self, cell: CellIndex) -> impl Iterator<Item = PatternIndex> + Clone + '_ {
+        let start_idx = cell.base * self.words_per_cell;
+        let t_count = self.t_count;
+
+        self.data[start_idx..start_idx + self.words_per_cell]
+            .iter()
+            .enumerate()
+            .flat_map(|(w_idx, &word)| {
+                // Each step clears the lowest set bit
+                std::iter::successors(Some(word), |w| Some(w & w.wrapping_sub(1)))
+                    .take_while(|&w| w != 0)
+                    .map(move |w| (w_idx << 6) + w.trailing_zeros() as usize)
+            })
+            .filter(move |&t_idx| t_idx < t_count)
+            .map(|t_idx| PatternIndex { base: t_idx })
+    }
+
     pub fn collapse_to_pattern<F>(
         &mut self,
         cell: CellIndex,
@@ -177,10 +219,24 @@ impl Wave {
         }
     }
 
-    pub fn is_fully_undetermined(&self, cell: CellIndex, entropy_tracker: &EntropyTracker) -> bool {
-        let current_count = entropy_tracker.possible_pattern_count(cell) as usize;
+    pub fn candidate_count(&self, cell: CellIndex) -> usize {
+        let start = cell.base * self.words_per_cell;
+        let words = &self.data[start..start + self.words_per_cell];
+
+        words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// How many of the cell's candidates are also in `mask`.
+    pub fn candidate_count_in(&self, cell: CellIndex, mask: &PatternBitSet) -> usize {
+        let start = cell.base * self.words_per_cell;
+        let words = &self.data[start..start + self.words_per_cell];
+
+        words.iter().zip(&mask.data).map(|(w, m)| (w & m).count_ones() as usize).sum()
+    }
 
-        // If the count matches the total patterns, no bans have occurred here
-        current_count == self.t_count
+    pub fn is_fully_undetermined(&self, cell: CellIndex) -> bool {
+        // Counted from the wave itself: during a rebuild the entropy tracker has
+        // just been reset and would report every cell as undetermined.
+        self.candidate_count(cell) == self.t_count
     }
 }
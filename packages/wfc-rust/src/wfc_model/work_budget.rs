@@ -0,0 +1,65 @@
+/// Upper bounds on the work a single [`Solver::step_for`] call may do.
+///
+/// [`Solver::step_for`]: crate::Solver::step_for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorkBudget {
+    /// Propagation stack pops.
+    pub max_pops: usize,
+
+    /// Patterns banned, including the bans made by collapsing a cell.
+    pub max_bans: usize,
+}
+
+impl WorkBudget {
+    pub const UNLIMITED: WorkBudget = WorkBudget {
+        max_pops: usize::MAX,
+        max_bans: usize::MAX,
+    };
+}
+
+// Reading the clock is slow compared to a stack pop, so the deadline is only
+// checked every few pops.
+const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+pub struct WorkLimit<'a> {
+    budget: WorkBudget,
+    pops: usize,
+    bans_start: usize,
+    deadline_reached: &'a mut dyn FnMut() -> bool,
+}
+
+impl<'a> WorkLimit<'a> {
+    pub fn new(budget: WorkBudget, ban_count: usize, deadline_reached: &'a mut dyn FnMut() -> bool) -> Self {
+        Self {
+            budget,
+            pops: 0,
+            bans_start: ban_count,
+            deadline_reached,
+        }
+    }
+
+    #[inline(always)]
+    pub fn record_pop(&mut self) {
+        self.pops += 1;
+    }
+
+    /// Checked between propagation pops.
+    pub fn exhausted_during_propagation(&mut self, ban_count: usize) -> bool {
+        if self.over_budget(ban_count) {
+            return true;
+        }
+
+        self.pops % DEADLINE_CHECK_INTERVAL == 0 && (self.deadline_reached)()
+    }
+
+    /// Checked before starting a new observation.
+    pub fn exhausted(&mut self, ban_count: usize) -> bool {
+        self.over_budget(ban_count) || (self.deadline_reached)()
+    }
+
+    fn over_budget(&self, ban_count: usize) -> bool {
+        let bans = ban_count - self.bans_start;
+
+        self.pops >= self.budget.max_pops || bans >= self.budget.max_bans
+    }
+}
@@ -0,0 +1,1326 @@
+//! Test suite for native (non-wasm) builds.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use rust_wfc::{
+    axial_to_offset, offset_to_axial, Backtracking, BoundaryMode, CellSelection, Direction, GraphTopology, GridDirection, GridShape,
+    HexDirection, IterationResult, MaskMode, MooreDirection, PatternChoice, PatternIndex, RestartPolicy, SideSpec, SnapshotPolicy,
+    Solver, VoxelDirection, WFCModel, WfcConfig, WfcConfigBuilder, WfcError, WorkBudget,
+};
+
+// Two patterns that may only sit next to each other: a checkerboard.
+fn checkerboard_model(width: usize, height: usize) -> WFCModel {
+    let t_count = 2;
+    let data = vec![1, 0];
+    let mut offsets = Vec::new();
+    let mut lengths = Vec::new();
+
+    for _d in 0..4 {
+        offsets.extend_from_slice(&[0, 1]);
+        lengths.extend_from_slice(&[1, 1]);
+    }
+
+    WFCModel::new(
+        width,
+        height,
+        1,
+        t_count,
+        vec![1.0, 1.0],
+        data,
+        offsets,
+        lengths,
+        GridShape::Square,
+        false,
+        false,
+        0.0,
+        0.5,
+        0.5,
+        CellSelection::MinEntropy,
+        PatternChoice::WeightedRandom,
+        1.0,
+        Backtracking::Trail,
+        10,
+        0.1,
+        64,
+        7,
+    )
+    .unwrap()
+}
+
+fn checkerboard_config(width: usize, height: usize) -> WfcConfigBuilder {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    WfcConfig::builder(width, height)
+        .weights(vec![1.0, 1.0])
+        .allow(a, Direction::East, b)
+        .allow(b, Direction::East, a)
+        .allow(a, Direction::South, b)
+        .allow(b, Direction::South, a)
+        .max_snapshots(10)
+}
+
+fn checkerboard_solver(width: usize, height: usize) -> Solver {
+    Solver::new(checkerboard_config(width, height).build().unwrap())
+}
+
+#[test]
+fn runs_to_completion_natively() {
+    let mut model = checkerboard_model(6, 4);
+    let mut result = IterationResult::STEP;
+
+    for _ in 0..model.get_total_cells() + 1 {
+        result = model.single_iteration_with_snapshots();
+        if result != IterationResult::STEP {
+            break;
+        }
+    }
+
+    assert_eq!(result, IterationResult::SUCCESS);
+    assert_eq!(model.get_filled_count(), model.get_total_cells());
+    assert!(model.get_total_memory_usage_bytes() > 0);
+}
+
+#[test]
+fn solver_builds_a_checkerboard() {
+    let mut solver = checkerboard_solver(5, 3);
+    let mut result = IterationResult::STEP;
+
+    while result == IterationResult::STEP {
+        result = solver.step();
+    }
+
+    assert_eq!(result, IterationResult::SUCCESS);
+
+    let first = solver.observed_at(0, 0).unwrap().base;
+    for y in 0..3 {
+        for x in 0..5 {
+            let expected = (first + x + y) % 2;
+            assert_eq!(solver.observed_at(x, y), Some(PatternIndex::new(expected)));
+        }
+    }
+}
+
+#[test]
+fn config_rejects_invalid_input() {
+    let err = WfcConfig::builder(4, 4)
+        .weights(vec![1.0])
+        .adjacency(PatternIndex::new(0), Direction::East, vec![PatternIndex::new(3)])
+        .build()
+        .unwrap_err();
+
+    assert_eq!(err, WfcError::PatternOutOfRange { pattern: PatternIndex::new(3), t_count: 1 });
+
+    let err = WfcConfig::builder(0, 4).weights(vec![1.0]).build().unwrap_err();
+
+    assert_eq!(err, WfcError::InvalidDimensions { width: 0, height: 4 });
+}
+
+#[test]
+fn config_rejects_degenerate_numbers() {
+    let huge = WfcConfig::builder(usize::MAX / 2, 4).weights(vec![1.0]).build().unwrap_err();
+    assert_eq!(huge, WfcError::InvalidDimensions { width: usize::MAX / 2, height: 4 });
+
+    let zero = WfcConfig::builder(4, 4).weights(vec![0.0, 0.0]).build().unwrap_err();
+    assert_eq!(zero, WfcError::ZeroWeights);
+
+    let bias = WfcConfig::builder(4, 4).weights(vec![1.0]).start_bias(f64::NAN, 0.5, 0.5).build();
+    assert!(matches!(bias, Err(WfcError::InvalidStartBias { .. })));
+
+    let interval = WfcConfig::builder(4, 4).weights(vec![1.0]).snapshot_interval_percent(f64::NAN).build();
+    assert!(matches!(interval, Err(WfcError::InvalidSnapshotInterval { .. })));
+}
+
+#[test]
+fn config_rejects_asymmetric_adjacency() {
+    // Pattern 1 may sit east of 0, but 0 may not sit west of 1.
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+    let err = WfcConfig::builder(4, 4)
+        .weights(vec![1.0, 1.0])
+        .allow(a, Direction::East, b)
+        .adjacency(b, Direction::West, vec![b])
+        .build()
+        .unwrap_err();
+
+    let east = Direction::East.into();
+    assert_eq!(err, WfcError::AsymmetricAdjacency { pattern: a, direction: east, neighbor: b });
+
+    // The same holds for the flat arrays: 0 allows 1 to its west, 1 allows nothing.
+    let err = WfcConfig::builder(4, 4)
+        .weights(vec![1.0, 1.0])
+        .propagator_arrays(vec![1, 0], vec![0, 0, 1, 0, 1, 1, 0, 0], vec![1, 0, 1, 0, 1, 0, 1, 0])
+        .build()
+        .unwrap_err();
+
+    assert!(matches!(err, WfcError::AsymmetricAdjacency { .. }));
+}
+
+fn run_to_end(solver: &mut Solver) -> IterationResult {
+    solver.run_until_complete(1).result
+}
+
+#[test]
+fn patterns_without_partners_are_banned() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    // `b` has no partner to the east or west, so it cannot appear in a row.
+    let config = |seed| {
+        WfcConfig::builder(2, 1)
+            .weights(vec![1.0, 100.0])
+            .allow(a, Direction::East, a)
+            .allow(a, Direction::South, a)
+            .allow(b, Direction::South, b)
+            .seed(seed)
+            .build()
+            .unwrap()
+    };
+
+    for seed in 0..8 {
+        let mut solver = Solver::new(config(seed));
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        assert_eq!(solver.observed_patterns(), vec![Some(a), Some(a)]);
+
+        solver.clear();
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        assert_eq!(solver.observed_patterns(), vec![Some(a), Some(a)]);
+    }
+}
+
+#[test]
+fn failure_lasts_until_clear() {
+    // An odd ring cannot be colored like a checkerboard, and nothing can be undone.
+    let config = checkerboard_config(3, 1).periodic_x(true).max_snapshots(0).build().unwrap();
+    let mut solver = Solver::new(config);
+
+    assert_eq!(solver.step(), IterationResult::FAIL);
+    assert_eq!(solver.step(), IterationResult::FAIL);
+    assert_eq!(solver.run(10, false).result, IterationResult::FAIL);
+    assert!(!solver.is_generation_complete());
+
+    // Opening the ring clears the solver, which can then finish.
+    solver.set_boundary_x(BoundaryMode::Open).unwrap();
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+}
+
+// Three interchangeable patterns leave every observation up to the PRNG.
+fn unconstrained_config(width: usize, height: usize) -> WfcConfigBuilder {
+    WfcConfig::builder(width, height)
+        .weights(vec![1.0, 2.0, 3.0])
+        .propagator_arrays(vec![0, 1, 2], vec![0; 12], vec![3; 12])
+}
+
+fn unconstrained_solver(seed: u64) -> Solver {
+    Solver::new(unconstrained_config(8, 8).seed(seed).build().unwrap())
+}
+
+#[test]
+fn same_seed_reproduces_output() {
+    let build = unconstrained_solver;
+
+    let mut a = build(42);
+    let mut b = build(42);
+    let mut c = build(43);
+
+    assert_eq!(run_to_end(&mut a), IterationResult::SUCCESS);
+    assert_eq!(run_to_end(&mut b), IterationResult::SUCCESS);
+    assert_eq!(run_to_end(&mut c), IterationResult::SUCCESS);
+    assert_eq!(a.observed_patterns(), b.observed_patterns());
+    assert_ne!(a.observed_patterns(), c.observed_patterns());
+
+    // Restoring the exported state replays the same run.
+    let state = b.rng_state();
+    b.clear();
+    run_to_end(&mut b);
+    let replay = b.observed_patterns();
+
+    b.set_rng_state(state);
+    b.clear();
+    run_to_end(&mut b);
+    assert_eq!(b.observed_patterns(), replay);
+}
+
+#[test]
+fn ties_are_broken_per_cell() {
+    let first_targets: Vec<usize> = (0..8)
+        .map(|seed| {
+            let mut solver = unconstrained_solver(seed);
+
+            solver.step();
+            solver.observed_patterns().iter().position(Option::is_some).unwrap()
+        })
+        .collect();
+
+    // Every cell starts with the same entropy; the lowest index must not always win.
+    assert!(first_targets.iter().any(|&idx| idx != first_targets[0]));
+}
+
+#[test]
+fn tie_breaks_never_outweigh_the_bias() {
+    // However small the bias, the cell at the start point is observed first.
+    for seed in 0..8 {
+        let config = unconstrained_config(8, 8).start_bias(1e-12, 0.0, 0.0).seed(seed).build().unwrap();
+        let mut solver = Solver::new(config);
+
+        solver.step();
+        assert_eq!(solver.observed_patterns().iter().position(Option::is_some), Some(0));
+    }
+}
+
+#[test]
+fn run_respects_step_budget_and_restarts() {
+    let mut solver = unconstrained_solver(1);
+    let partial = solver.run(2, false);
+
+    assert_eq!(partial.steps, 2);
+    assert_eq!(partial.result, IterationResult::STEP);
+    assert_eq!(solver.run(1000, false).result, IterationResult::SUCCESS);
+
+    // A checkerboard cannot wrap around an odd number of columns.
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+    let config = WfcConfig::builder(3, 2)
+        .weights(vec![1.0, 1.0])
+        .allow(a, Direction::East, b)
+        .allow(b, Direction::East, a)
+        .allow(a, Direction::South, b)
+        .allow(b, Direction::South, a)
+        .periodic(true)
+        .max_snapshots(4)
+        .build()
+        .unwrap();
+    let mut impossible = Solver::new(config);
+    let summary = impossible.run_until_complete(3);
+
+    assert_eq!(summary.result, IterationResult::FAIL);
+    assert_eq!(summary.restarts, 2);
+    assert!(summary.reverts >= 3);
+}
+
+#[test]
+fn snapshot_revert_rebuilds_entropy_from_the_wave() {
+    // Three free patterns: every observation settles exactly one cell.
+    let mut model = WFCModel::new(
+        4,
+        4,
+        1,
+        3,
+        vec![1.0, 2.0, 3.0],
+        vec![0, 1, 2],
+        vec![0; 12],
+        vec![3; 12],
+        GridShape::Square,
+        false,
+        false,
+        0.0,
+        0.5,
+        0.5,
+        CellSelection::MinEntropy,
+        PatternChoice::WeightedRandom,
+        1.0,
+        Backtracking::Snapshot,
+        10,
+        0.0,
+        0,
+        11,
+    )
+    .unwrap();
+    let entropies = |model: &WFCModel| unsafe { std::slice::from_raw_parts(model.entropies_ptr(), 16).to_vec() };
+    let open = entropies(&model)[0];
+
+    for _ in 0..5 {
+        assert_eq!(model.single_iteration_with_snapshots(), IterationResult::STEP);
+    }
+    assert!(model.revert());
+
+    // The four cells observed before the reverted one are rebuilt as settled,
+    // and only the reverted cell is partly banned.
+    let after = entropies(&model);
+    assert_eq!(model.get_filled_count(), 4);
+    assert_eq!(after.iter().filter(|&&e| e == 0.0).count(), 4);
+    assert_eq!(after.iter().filter(|&&e| e == open).count(), 11);
+}
+
+#[test]
+fn step_for_resumes_across_calls() {
+    let mut stepped = checkerboard_solver(16, 16);
+    let mut budgeted = checkerboard_solver(16, 16);
+    let budget = WorkBudget { max_pops: 3, max_bans: usize::MAX };
+    let mut calls = 0;
+
+    assert_eq!(run_to_end(&mut stepped), IterationResult::SUCCESS);
+
+    loop {
+        let summary = budgeted.step_for(budget, || false);
+        calls += 1;
+
+        if summary.result == IterationResult::SUCCESS {
+            break;
+        }
+        assert!(calls < 10_000);
+    }
+
+    // One observation floods the whole grid, so it must have been split up.
+    assert!(calls > 2);
+    assert!(!budgeted.is_propagation_pending());
+    assert_eq!(budgeted.observed_patterns(), stepped.observed_patterns());
+}
+
+#[test]
+fn pinned_cells_survive_clear() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+    let mut solver = checkerboard_solver(4, 4);
+
+    solver.set_cell(0, 0, b).unwrap();
+
+    // The pin alone decides the whole checkerboard.
+    assert_eq!(solver.filled_count(), solver.total_cells());
+    assert_eq!(solver.observed_at(1, 0), Some(a));
+
+    assert_eq!(solver.set_cell(1, 0, b), Err(WfcError::Contradiction { x: 1, y: 0 }));
+    assert_eq!(solver.ban_at(9, 0, a), Err(WfcError::CellOutOfRange { x: 9, y: 0 }));
+
+    solver.clear();
+    assert_eq!(solver.observed_at(0, 0), Some(b));
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    assert_eq!(solver.observed_at(3, 3), Some(b));
+
+    solver.clear_constraints();
+    assert_eq!(solver.observed_at(0, 0), None);
+}
+
+#[test]
+fn repeated_constraints_store_nothing_new() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+    let mut solver = checkerboard_solver(4, 4);
+
+    solver.set_cell(0, 0, b).unwrap();
+
+    let stored = solver.memory_usage_bytes();
+    for _ in 0..100 {
+        solver.set_cell(0, 0, b).unwrap();
+        solver.ban_at(0, 0, a).unwrap();
+    }
+    assert_eq!(solver.memory_usage_bytes(), stored);
+}
+
+#[test]
+fn rejected_constraints_leave_the_solver_unchanged() {
+    let mut solver = unconstrained_solver(3);
+    for _ in 0..10 {
+        assert_eq!(solver.step(), IterationResult::STEP);
+    }
+    let observed = solver.observed_patterns();
+
+    // No pattern at all is a contradiction whatever the rules.
+    assert_eq!(solver.restrict_cell(5, 5, &[]), Err(WfcError::Contradiction { x: 5, y: 5 }));
+    assert_eq!(solver.observed_patterns(), observed);
+    assert_eq!(solver.filled_count(), 10);
+    assert!(solver.is_candidate_at(5, 5, PatternIndex::new(0)));
+
+    // Generation carries on from where it was.
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    let kept = observed.iter().zip(solver.observed_patterns()).all(|(&before, after)| before.is_none() || before == after);
+    assert!(kept);
+}
+
+#[test]
+fn regenerate_region_keeps_the_surroundings() {
+    let mut solver = unconstrained_solver(5);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    let before = solver.observed_patterns();
+
+    solver.regenerate_region(2, 3, 4, 2).unwrap();
+    assert_eq!(solver.filled_count(), solver.total_cells() - 8);
+    assert_eq!(solver.observed_at(2, 3), None);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+
+    let after = solver.observed_patterns();
+    for y in 0..8 {
+        for x in 0..8 {
+            let inside = (2..6).contains(&x) && (3..5).contains(&y);
+            if !inside {
+                assert_eq!(after[y * 8 + x], before[y * 8 + x]);
+            }
+        }
+    }
+
+    // Fixed neighbors alone decide a checkerboard region.
+    let mut board = checkerboard_solver(6, 6);
+    assert_eq!(run_to_end(&mut board), IterationResult::SUCCESS);
+    let expected = board.observed_patterns();
+
+    board.regenerate_region(1, 1, 3, 3).unwrap();
+    assert_eq!(board.filled_count(), board.total_cells());
+    assert_eq!(board.observed_patterns(), expected);
+}
+
+#[test]
+fn regenerate_region_rejects_rectangles_past_the_edge() {
+    let mut board = checkerboard_solver(6, 6);
+    assert_eq!(run_to_end(&mut board), IterationResult::SUCCESS);
+
+    // The error names the first column or row past the edge.
+    assert_eq!(board.regenerate_region(4, 4, 3, 1), Err(WfcError::CellOutOfRange { x: 6, y: 4 }));
+    assert_eq!(
+        board.regenerate_region(usize::MAX, 0, 2, 1),
+        Err(WfcError::CellOutOfRange { x: usize::MAX, y: 0 })
+    );
+
+    // Only single-layer grids can be regenerated.
+    let config = WfcConfig::builder(2, 2).shape(GridShape::Voxel).depth(2).weights(vec![1.0]).build().unwrap();
+    let mut voxels = Solver::new(config);
+    assert_eq!(voxels.regenerate_region(0, 0, 1, 1), Err(WfcError::InvalidDepth { depth: 2 }));
+}
+
+#[test]
+fn unfillable_regions_leave_the_solver_as_it_was() {
+    // An odd ring fails, and so does every region cut out of it.
+    let config = checkerboard_config(3, 2).periodic_x(true).build().unwrap();
+    let mut odd = Solver::new(config);
+    assert_eq!(run_to_end(&mut odd), IterationResult::FAIL);
+    let state = |solver: &Solver| {
+        let candidates: Vec<bool> = (0..6)
+            .flat_map(|i| (0..2).map(move |t| (i, t)))
+            .map(|(i, t)| solver.is_candidate_at(i % 3, i / 3, PatternIndex::new(t)))
+            .collect();
+        (solver.observed_patterns(), solver.filled_count(), candidates, solver.learned_nogood_count())
+    };
+    let before = state(&odd);
+
+    assert_eq!(odd.regenerate_region(1, 0, 1, 1), Err(WfcError::Contradiction { x: 1, y: 0 }));
+    assert_eq!(before.3, 1);
+    assert_eq!(state(&odd), before);
+}
+
+#[test]
+fn boundary_modes_apply_per_axis() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    // An odd width cannot wrap horizontally, but an even height can wrap vertically.
+    let mut cylinder = Solver::new(checkerboard_config(3, 2).periodic_x(true).build().unwrap());
+    assert_eq!(run_to_end(&mut cylinder), IterationResult::FAIL);
+    let mut cylinder = Solver::new(checkerboard_config(3, 2).periodic_y(true).build().unwrap());
+    assert_eq!(run_to_end(&mut cylinder), IterationResult::SUCCESS);
+
+    // `a` sits past the west and east edges, so a single row must start and end with `b`.
+    let config = checkerboard_config(5, 1).boundary_x(BoundaryMode::Fixed(a)).build().unwrap();
+    let fixed = Solver::new(config);
+    assert_eq!(fixed.filled_count(), fixed.total_cells());
+    assert_eq!(fixed.observed_at(0, 0), Some(b));
+    assert_eq!(fixed.observed_at(4, 0), Some(b));
+
+    // Taller, the edge columns would need `b` in every row.
+    let config = checkerboard_config(5, 2).boundary_x(BoundaryMode::Fixed(a)).build().unwrap();
+    assert_eq!(run_to_end(&mut Solver::new(config)), IterationResult::FAIL);
+
+    // The top and bottom rows of an even-height board cannot both start with `a`.
+    let config = checkerboard_config(4, 4)
+        .boundary_y(BoundaryMode::EdgeCompatible)
+        .edge_patterns(vec![a])
+        .build()
+        .unwrap();
+    let mut edges = Solver::new(config);
+    assert_eq!(run_to_end(&mut edges), IterationResult::FAIL);
+
+    edges.set_boundary_y(BoundaryMode::Open).unwrap();
+    assert_eq!(run_to_end(&mut edges), IterationResult::SUCCESS);
+    assert!(edges.set_boundary_x(BoundaryMode::Fixed(PatternIndex::new(5))).is_err());
+
+    let out_of_range = checkerboard_config(4, 4).edge_patterns(vec![PatternIndex::new(2)]).build();
+    assert!(out_of_range.is_err());
+}
+
+#[test]
+fn side_specs_survive_clear() {
+    let (a, b, c) = (PatternIndex::new(0), PatternIndex::new(1), PatternIndex::new(2));
+    let mut solver = unconstrained_solver(11);
+
+    solver.set_side(Direction::South, SideSpec::Fixed(c)).unwrap();
+    solver.set_side(Direction::North, SideSpec::Allowed(vec![a, b])).unwrap();
+    assert_eq!(solver.filled_count(), 8);
+
+    for _ in 0..2 {
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        for x in 0..8 {
+            assert_eq!(solver.observed_at(x, 7), Some(c));
+            assert_ne!(solver.observed_at(x, 0), Some(c));
+        }
+        solver.clear();
+    }
+
+    let out_of_range = SideSpec::Allowed(vec![PatternIndex::new(3)]);
+    assert!(solver.set_side(Direction::East, out_of_range).is_err());
+    solver.set_side(Direction::South, SideSpec::Any).unwrap();
+    assert_eq!(solver.filled_count(), 0);
+}
+
+#[test]
+fn hex_grid_propagates_six_directions() {
+    // Pattern `c` needs `c + 1` east, `c + 2` north-east and `c + 1` north-west.
+    // That colors each cell by (q - r) mod 3, so one observation decides the grid.
+    let hex_rules = |width: usize, height: usize| {
+        let mut builder = WfcConfig::builder(width, height).weights(vec![1.0; 3]).shape(GridShape::Hex);
+        for c in 0..3 {
+            let p = PatternIndex::new(c);
+            builder = builder
+                .allow(p, HexDirection::East, PatternIndex::new((c + 1) % 3))
+                .allow(p, HexDirection::NorthEast, PatternIndex::new((c + 2) % 3))
+                .allow(p, HexDirection::NorthWest, PatternIndex::new((c + 1) % 3));
+        }
+        builder
+    };
+
+    // Wrapping needs a width divisible by 3 to keep the coloring consistent.
+    for &(width, height, periodic) in &[(7, 5, false), (6, 4, true)] {
+        let mut solver = Solver::new(hex_rules(width, height).periodic(periodic).build().unwrap());
+        assert_eq!(solver.step(), IterationResult::STEP);
+        assert_eq!(solver.filled_count(), solver.total_cells());
+
+        let color = |x: i32, y: i32| solver.observed_at(x as usize, y as usize).unwrap().base as i32;
+        let base = color(0, 0);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let (q, r) = offset_to_axial(x, y);
+                assert_eq!(axial_to_offset(q, r), (x, y));
+                assert_eq!(color(x, y), (base + q - r).rem_euclid(3));
+            }
+        }
+    }
+
+    let mut cylinder = Solver::new(hex_rules(7, 4).periodic_x(true).build().unwrap());
+    assert_eq!(cylinder.step(), IterationResult::FAIL);
+
+    // An odd height would break the row parity the layout depends on.
+    let odd = WfcConfig::builder(3, 3).weights(vec![1.0]).shape(GridShape::Hex).periodic_y(true).build();
+    assert_eq!(odd.err(), Some(WfcError::InvalidDimensions { width: 3, height: 3 }));
+
+    let wrong_shape = WfcConfig::builder(3, 3).weights(vec![1.0; 3]).allow(
+        PatternIndex::new(0),
+        HexDirection::SouthEast,
+        PatternIndex::new(1),
+    );
+    assert!(wrong_shape.build().is_err());
+}
+
+// A 4x3 checkerboard `depth` layers deep.
+fn voxel_rules(depth: usize) -> WfcConfigBuilder {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    WfcConfig::builder(4, 3)
+        .depth(depth)
+        .shape(GridShape::Voxel)
+        .weights(vec![1.0, 1.0])
+        .allow(a, VoxelDirection::PosX, b)
+        .allow(b, VoxelDirection::PosX, a)
+        .allow(a, VoxelDirection::PosY, b)
+        .allow(b, VoxelDirection::PosY, a)
+        .allow(a, VoxelDirection::PosZ, b)
+        .allow(b, VoxelDirection::PosZ, a)
+}
+
+#[test]
+fn voxel_grid_propagates_along_z() {
+
+    // A 3D checkerboard: one observation decides every layer.
+    let mut solver = Solver::new(voxel_rules(5).start_z(0.0).build().unwrap());
+    assert_eq!(solver.total_cells(), 4 * 3 * 5);
+    assert_eq!(solver.step(), IterationResult::STEP);
+    assert_eq!(solver.filled_count(), solver.total_cells());
+
+    let first = solver.observed_at_3d(0, 0, 0).unwrap();
+    for z in 0..5 {
+        for y in 0..3 {
+            for x in 0..4 {
+                let same = (x + y + z) % 2 == 0;
+                assert_eq!(solver.observed_at_3d(x, y, z) == Some(first), same);
+            }
+        }
+    }
+    assert_eq!(solver.observed_at_3d(0, 0, 5), None);
+
+    // Wrapping an odd number of layers breaks the alternation.
+    let mut wrapped = Solver::new(voxel_rules(5).periodic_z(true).build().unwrap());
+    assert_eq!(run_to_end(&mut wrapped), IterationResult::FAIL);
+    let mut wrapped = Solver::new(voxel_rules(4).periodic_z(true).build().unwrap());
+    assert_eq!(run_to_end(&mut wrapped), IterationResult::SUCCESS);
+
+    let flat = WfcConfig::builder(4, 4).depth(2).weights(vec![1.0]).build();
+    assert_eq!(flat.err(), Some(WfcError::InvalidDepth { depth: 2 }));
+}
+
+#[test]
+fn voxel_cells_take_constraints() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+    let mut solver = Solver::new(voxel_rules(3).build().unwrap());
+
+    // Pinning a cell on the top layer decides the whole block.
+    solver.set_cell_3d(1, 1, 2, a).unwrap();
+    assert_eq!(solver.filled_count(), solver.total_cells());
+    assert_eq!(solver.observed_at_3d(1, 1, 2), Some(a));
+    assert_eq!(solver.observed_at_3d(1, 1, 1), Some(b));
+    assert!(solver.is_candidate_at_3d(1, 1, 0, a));
+    assert!(!solver.is_candidate_at_3d(1, 1, 0, b));
+    assert!(!solver.is_candidate_at_3d(1, 1, 3, a));
+
+    assert_eq!(solver.ban_at_3d(1, 1, 0, a), Err(WfcError::Contradiction { x: 1, y: 1 }));
+    assert_eq!(solver.restrict_cell_3d(0, 0, 3, &[a]), Err(WfcError::LayerOutOfRange { z: 3, depth: 3 }));
+    assert_eq!(solver.observed_at_3d(1, 1, 2), Some(a));
+}
+
+#[test]
+fn graph_topology_uses_edge_labels() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    // A ring where each node links to the next through label 0, and back through label 1.
+    let ring = |nodes: usize| {
+        let mut graph = GraphTopology::new(nodes, vec![1, 0]).unwrap();
+        for i in 0..nodes {
+            graph.connect(i, 0, (i + 1) % nodes).unwrap();
+        }
+
+        let next = graph.label(0);
+        let config = WfcConfig::graph_builder(graph)
+            .weights(vec![1.0, 1.0])
+            .allow(a, next, b)
+            .allow(b, next, a)
+            .build()
+            .unwrap();
+        Solver::new(config)
+    };
+
+    let mut even = ring(6);
+    assert_eq!(run_to_end(&mut even), IterationResult::SUCCESS);
+    for i in 0..6 {
+        assert_ne!(even.observed_at(i, 0), even.observed_at((i + 1) % 6, 0));
+    }
+
+    let mut odd = ring(5);
+    assert_eq!(run_to_end(&mut odd), IterationResult::FAIL);
+
+    let mut graph = GraphTopology::new(3, vec![0]).unwrap();
+    graph.connect(0, 0, 1).unwrap();
+    assert_eq!(graph.connect(1, 0, 2), Err(WfcError::InvalidGraphEdge { node: 1, label: 0 }));
+    assert_eq!(graph.connect(2, 0, 3), Err(WfcError::InvalidGraphEdge { node: 3, label: 0 }));
+    assert!(GraphTopology::new(3, vec![1, 1]).is_err());
+}
+
+#[test]
+fn custom_topologies_run_through_propagate() {
+    let (a, b, c) = (PatternIndex::new(0), PatternIndex::new(1), PatternIndex::new(2));
+
+    // Node 0 leads to node 1 through label 0, node 1 to node 2 through the
+    // self-opposite label 2, and node 3 is linked to nothing.
+    let mut graph = GraphTopology::new(4, vec![1, 0, 2]).unwrap();
+    graph.connect(0, 0, 1).unwrap();
+    graph.connect(1, 2, 2).unwrap();
+
+    let (next, same) = (graph.label(0), graph.label(2));
+    let config = WfcConfig::graph_builder(graph)
+        .weights(vec![1.0, 1.0, 1.0])
+        .allow(a, next, b)
+        .allow(b, next, c)
+        .allow(c, next, a)
+        .allow(a, same, a)
+        .allow(b, same, b)
+        .allow(c, same, c)
+        .build()
+        .unwrap();
+    let mut solver = Solver::new(config);
+
+    solver.set_cell(1, 0, b).unwrap();
+    assert!(solver.propagate());
+
+    let candidates = |solver: &Solver, node: usize| -> Vec<PatternIndex> {
+        [a, b, c].iter().copied().filter(|&p| solver.is_candidate_at(node, 0, p)).collect()
+    };
+    assert_eq!(candidates(&solver, 0), vec![a]);
+    assert_eq!(candidates(&solver, 2), vec![b]);
+    assert_eq!(candidates(&solver, 3), vec![a, b, c]);
+}
+
+#[test]
+fn masked_cells_are_left_out() {
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    // A 4x4 checkerboard with a hole at (1, 1).
+    let mut active = vec![true; 16];
+    active[5] = false;
+
+    let config = checkerboard_config(4, 4).mask(active.clone(), MaskMode::Void(a)).build().unwrap();
+    let mut solver = Solver::new(config);
+
+    assert_eq!(solver.total_cells(), 15);
+    assert!(solver.set_cell(1, 1, a).is_err());
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    assert_eq!(solver.filled_count(), 15);
+    assert_eq!(solver.filled_percent(), 1.0);
+    assert_eq!(solver.observed_at(1, 1), None);
+
+    // The void counts as `a`, so its neighbors and everything on their parity are `b`.
+    assert_eq!(solver.observed_at(0, 1), Some(b));
+    assert_eq!(solver.observed_at(0, 0), Some(a));
+
+    // Cutting a strip in two lets each half pick its own parity.
+    let open = |seed| {
+        let config = checkerboard_config(5, 1)
+            .mask(vec![true, true, false, true, true], MaskMode::Open)
+            .seed(seed)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        solver.observed_at(0, 0) == solver.observed_at(3, 0)
+    };
+    let outcomes: Vec<bool> = (0..16).map(open).collect();
+    assert!(outcomes.contains(&true) && outcomes.contains(&false));
+
+    // Masks can also be swapped on a running solver.
+    let mut solver = checkerboard_solver(4, 4);
+    solver.set_mask(active.clone(), MaskMode::Void(a)).unwrap();
+    assert_eq!(solver.total_cells(), 15);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    assert_eq!(solver.observed_at(0, 0), Some(a));
+    solver.clear_mask();
+    assert_eq!(solver.total_cells(), 16);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    assert!(solver.observed_at(1, 1).is_some());
+    let empty = solver.set_mask(vec![false; 16], MaskMode::Open);
+    assert_eq!(empty.err(), Some(WfcError::EmptyMask));
+
+    let short = checkerboard_config(4, 4).mask(vec![true; 15], MaskMode::Open).build();
+    assert_eq!(short.err(), Some(WfcError::InvalidMask { expected: 16, actual: 15 }));
+    let out_of_range = checkerboard_config(4, 4).mask(active, MaskMode::Void(PatternIndex::new(2))).build();
+    assert!(out_of_range.is_err());
+}
+
+#[test]
+fn unsatisfiable_void_masks_fail_on_the_first_step() {
+    let (a, b, void) = (PatternIndex::new(0), PatternIndex::new(1), PatternIndex::new(2));
+
+    // `a` and `b` go anywhere, but only `a` may sit above the void and only `b` below it.
+    let mut builder = WfcConfig::builder(5, 4).weights(vec![1.0, 1.0, 1.0]);
+    for &p in &[a, b] {
+        for &q in &[a, b] {
+            builder = builder.allow(p, Direction::East, q).allow(p, Direction::South, q);
+        }
+        builder = builder.allow(p, Direction::East, void).allow(void, Direction::East, p);
+    }
+    builder = builder.allow(a, Direction::South, void).allow(void, Direction::South, b);
+
+    // (0, 2) is walled in by the void above, below and to the right.
+    let mut active = vec![true; 20];
+    for &(x, y) in &[(0, 1), (0, 3), (1, 2)] {
+        active[y * 5 + x] = false;
+    }
+
+    for &backtracking in &[Backtracking::Snapshot, Backtracking::Trail] {
+        let config = builder
+            .clone()
+            .mask(active.clone(), MaskMode::Void(void))
+            .backtracking(backtracking)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(solver.step(), IterationResult::FAIL);
+    }
+}
+
+#[test]
+fn moore_grid_propagates_diagonals() {
+    use MooreDirection::*;
+
+    let (a, b) = (PatternIndex::new(0), PatternIndex::new(1));
+
+    // Orthogonal neighbors are free, diagonal ones must match.
+    let rules = |width, height| {
+        let mut builder = WfcConfig::builder(width, height).shape(GridShape::Moore).weights(vec![1.0, 1.0]);
+        for &(p, q) in &[(a, a), (a, b), (b, a), (b, b)] {
+            builder = builder.allow(p, East, q).allow(p, South, q);
+        }
+        for &p in &[a, b] {
+            builder = builder.allow(p, SouthEast, p).allow(p, NorthEast, p);
+        }
+        builder
+    };
+
+    let mut solver = Solver::new(rules(6, 4).build().unwrap());
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    for y in 0..3 {
+        for x in 0..5 {
+            assert_eq!(solver.observed_at(x, y), solver.observed_at(x + 1, y + 1));
+            assert_eq!(solver.observed_at(x, y + 1), solver.observed_at(x + 1, y));
+        }
+    }
+
+    // Wrapping an odd width links the diagonals of both parities.
+    let mut wrapped = Solver::new(rules(5, 4).periodic(true).seed(7).build().unwrap());
+    assert_eq!(run_to_end(&mut wrapped), IterationResult::SUCCESS);
+    let first = wrapped.observed_at(0, 0);
+    assert!((0..4).all(|y| (0..5).all(|x| wrapped.observed_at(x, y) == first)));
+
+    assert_eq!(NorthWest.opposite(), SouthEast);
+    assert_eq!(West.opposite(), East);
+}
+
+#[test]
+fn observation_order_follows_bias_on_large_grids() {
+    let size = 96;
+    let config = unconstrained_config(size, size)
+        .start_bias(1.0, 0.25, 0.75)
+        .max_snapshots(0)
+        .build()
+        .unwrap();
+    let mut solver = Solver::new(config);
+    let (cx, cy) = (95.0 * 0.25, 95.0 * 0.75);
+
+    // Entropies stay equal while nothing propagates, so cells are observed
+    // in order of their distance from the start.
+    let mut last_distance = 0.0;
+    for _ in 0..size * size {
+        assert_eq!(solver.step(), IterationResult::STEP);
+
+        let changes = solver.take_changes();
+        assert_eq!(changes.len(), 1);
+
+        let (x, y) = ((changes[0] as usize % size) as f64, (changes[0] as usize / size) as f64);
+        let distance = ((x - cx).powi(2) + (y - cy).powi(2)).sqrt();
+        assert!(distance >= last_distance - 1e-5);
+        last_distance = distance;
+    }
+    assert_eq!(solver.step(), IterationResult::SUCCESS);
+    assert_eq!(solver.filled_percent(), 1.0);
+}
+
+#[test]
+fn a_single_pattern_fills_the_grid() {
+    let config = WfcConfig::builder(3, 3)
+        .weights(vec![1.0])
+        .propagator_arrays(vec![0], vec![0; 4], vec![1; 4])
+        .build()
+        .unwrap();
+    let mut solver = Solver::new(config);
+    let only = Some(PatternIndex::new(0));
+
+    // Every cell is determined from the start, so there is nothing to observe.
+    assert_eq!(solver.step(), IterationResult::SUCCESS);
+    assert!(solver.observed_patterns().iter().all(|&p| p == only));
+
+    solver.regenerate_region(0, 0, 2, 2).unwrap();
+    assert_eq!(solver.step(), IterationResult::SUCCESS);
+    assert!(solver.observed_patterns().iter().all(|&p| p == only));
+}
+
+#[test]
+fn cell_selection_orders_observation() {
+    // With nothing to propagate, each step collapses exactly the selected cell.
+    let order = |size: usize, selection, seed| {
+        let config = unconstrained_config(size, size)
+            .selection(selection)
+            .seed(seed)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        let mut cells = Vec::new();
+        while solver.step() == IterationResult::STEP {
+            cells.extend(solver.take_changes().into_iter().map(|c| (c as usize % size, c as usize / size)));
+        }
+        assert_eq!(cells.len(), size * size);
+        cells
+    };
+
+    let scanline = order(16, CellSelection::Scanline, 1);
+    assert!(scanline.iter().enumerate().all(|(i, &(x, y))| y * 16 + x == i));
+
+    let hilbert = order(16, CellSelection::Hilbert, 1);
+    assert!(hilbert.windows(2).all(|w| w[0].0.abs_diff(w[1].0) + w[0].1.abs_diff(w[1].1) == 1));
+
+    let spiral = order(15, CellSelection::Spiral, 1);
+    let rings: Vec<usize> = spiral.iter().map(|&(x, y)| x.abs_diff(7).max(y.abs_diff(7))).collect();
+    assert_eq!(spiral[0], (7, 7));
+    assert!(rings.windows(2).all(|w| w[0] <= w[1]));
+
+    let random = order(16, CellSelection::Random, 1);
+    assert_ne!(random, scanline);
+    assert_ne!(random, order(16, CellSelection::Random, 2));
+    assert_eq!(random, order(16, CellSelection::Random, 1));
+
+    let config = checkerboard_config(6, 4).selection(CellSelection::MinRemaining).build().unwrap();
+    assert_eq!(run_to_end(&mut Solver::new(config)), IterationResult::SUCCESS);
+}
+
+#[test]
+fn pattern_choice_strategies() {
+    let (a, b, c) = (PatternIndex::new(0), PatternIndex::new(1), PatternIndex::new(2));
+    let count = |solver: &Solver, p| solver.observed_patterns().iter().filter(|&&o| o == Some(p)).count();
+
+    // Unconstrained patterns, so only the chooser shapes the output.
+    let free = |choice, temperature| {
+        let config = unconstrained_config(32, 32)
+            .pattern_choice(choice)
+            .temperature(temperature)
+            .seed(5)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        solver
+    };
+
+    assert_eq!(count(&free(PatternChoice::MaxWeight, 1.0), c), 1024);
+    assert!(count(&free(PatternChoice::Temperature, 0.05), c) > 1000);
+    assert!(count(&free(PatternChoice::WeightedRandom, 1.0), a) < 250);
+    assert!(count(&free(PatternChoice::Temperature, 100.0), a) > 250);
+
+    // `a` and `b` ban each other, while the rare `c` fits anywhere and bans nothing.
+    let exclusive = |choice| {
+        let mut builder = WfcConfig::builder(8, 8).weights(vec![1.0, 1.0, 0.01]).pattern_choice(choice);
+        for &d in &[Direction::East, Direction::South] {
+            for &(p, q) in &[(a, a), (b, b), (a, c), (b, c), (c, a), (c, b), (c, c)] {
+                builder = builder.allow(p, d, q);
+            }
+        }
+        let mut solver = Solver::new(builder.build().unwrap());
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        count(&solver, c)
+    };
+    assert!(exclusive(PatternChoice::LeastConstraining) > 32);
+    assert!(exclusive(PatternChoice::WeightedRandom) < 8);
+
+    let frozen = WfcConfig::builder(4, 4).weights(vec![1.0]).temperature(0.0).build();
+    assert_eq!(frozen.err(), Some(WfcError::InvalidTemperature { temperature: 0.0 }));
+}
+
+#[test]
+fn trail_revert_restores_the_previous_state() {
+    let candidates = |solver: &Solver| {
+        let mut all = Vec::new();
+        for y in 0..4 {
+            for x in 0..4 {
+                all.extend((0..2).map(|t| solver.is_candidate_at(x, y, PatternIndex::new(t))));
+            }
+        }
+        all
+    };
+
+    let mut solver = checkerboard_solver(4, 4);
+    let before = candidates(&solver);
+
+    // One observation settles the whole board.
+    assert_eq!(solver.step(), IterationResult::STEP);
+    assert_eq!(solver.filled_count(), 16);
+    let first = solver.observed_patterns();
+
+    // Undoing it leaves only the chosen pattern banned at the observed cell.
+    assert!(solver.revert());
+    assert_eq!(solver.filled_count(), 0);
+    let after = candidates(&solver);
+    let differences: Vec<usize> = (0..32).filter(|&i| before[i] != after[i]).collect();
+    assert_eq!(differences.len(), 1);
+    assert!(solver.is_propagation_pending());
+
+    // The opposite board follows once the ban propagates.
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    let second = solver.observed_patterns();
+    assert!((0..16).all(|i| second[i] != first[i]));
+    assert!(!solver.revert());
+
+    // A periodic board with an odd width cannot be colored, so every decision is undone.
+    for &backtracking in &[Backtracking::Trail, Backtracking::Snapshot] {
+        let config = checkerboard_config(3, 2).periodic_x(true).backtracking(backtracking).build().unwrap();
+        assert_eq!(run_to_end(&mut Solver::new(config)), IterationResult::FAIL);
+    }
+}
+
+#[test]
+fn trail_forgets_the_bans_of_dropped_decisions() {
+    let free_board = |max_snapshots| {
+        let config = unconstrained_config(32, 32).max_snapshots(max_snapshots).seed(5).build().unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        solver
+    };
+
+    // The same output either way, but a short trail stays small.
+    let (short, long) = (free_board(2), free_board(2000));
+    assert_eq!(short.observed_patterns(), long.observed_patterns());
+    assert!(short.memory_usage_bytes() + 64 * 1024 < long.memory_usage_bytes());
+}
+
+#[test]
+fn candidates_past_the_pattern_count_are_rejected() {
+    let solver = checkerboard_solver(4, 4);
+
+    assert!(solver.is_candidate_at(0, 0, PatternIndex::new(1)));
+    assert!(!solver.is_candidate_at(0, 0, PatternIndex::new(2)));
+    assert!(!solver.is_candidate_at(0, 0, PatternIndex::new(64)));
+}
+
+// Neighbors must differ, which greedy observation sometimes paints into a corner.
+fn three_colors(width: usize, height: usize) -> WfcConfigBuilder {
+    let mut builder = WfcConfig::builder(width, height).weights(vec![1.0; 3]).max_snapshots(100);
+    for &d in &[Direction::East, Direction::South] {
+        for (p, q) in (0..3).flat_map(|p| (0..3).map(move |q| (p, q))).filter(|(p, q)| p != q) {
+            builder = builder.allow(PatternIndex::new(p), d, PatternIndex::new(q));
+        }
+    }
+    builder
+}
+
+#[test]
+fn contradictions_are_learned_as_nogoods() {
+    let learning = |seed, max_nogoods| {
+        let config = three_colors(12, 12).max_nogoods(max_nogoods).seed(seed).build().unwrap();
+        Solver::new(config)
+    };
+
+    let mut solver = learning(6, 64);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+    assert_eq!(solver.learned_nogood_count(), 2);
+
+    let observed = solver.observed_patterns();
+    for i in 0..144 {
+        let right = observed.get(i + 1).filter(|_| i % 12 < 11);
+        let below = observed.get(i + 12);
+        assert!(right.into_iter().chain(below).all(|&p| p != observed[i]));
+    }
+
+    let mut capped = learning(6, 1);
+    assert_eq!(run_to_end(&mut capped), IterationResult::SUCCESS);
+    assert_eq!(capped.learned_nogood_count(), 1);
+
+    // Nogoods only last for one attempt.
+    solver.clear();
+    assert_eq!(solver.learned_nogood_count(), 0);
+
+    let mut unlearned = learning(6, 0);
+    assert_eq!(run_to_end(&mut unlearned), IterationResult::SUCCESS);
+    assert_eq!(unlearned.learned_nogood_count(), 0);
+
+    // The odd periodic board fails on its first observation.
+    let config = checkerboard_config(3, 2).periodic_x(true).build().unwrap();
+    let mut solver = Solver::new(config);
+    assert_eq!(run_to_end(&mut solver), IterationResult::FAIL);
+    assert_eq!(solver.learned_nogood_count(), 1);
+}
+
+#[test]
+fn backjumping_skips_unrelated_observations() {
+    // Node 0 is a hub linked to the triangle 5-6-7, and nodes 1-4 stand alone.
+    // Triangle neighbors differ, and a hub holding pattern 0 keeps 0 out of the
+    // triangle, which then cannot be finished. Nothing shows that until the
+    // triangle is observed, after the unrelated nodes 1-4.
+    let trap = |backtracking| {
+        let mut graph = GraphTopology::new(8, vec![1, 0, 5, 6, 7, 2, 3, 4]).unwrap();
+        for (i, &node) in [5, 6, 7].iter().enumerate() {
+            graph.connect(node, 0, 5 + (i + 1) % 3).unwrap();
+            graph.connect(0, 2 + i, node).unwrap();
+        }
+
+        let ring = graph.label(0);
+        let mut builder = WfcConfig::graph_builder(graph.clone())
+            .weights(vec![3.0, 2.0, 1.0])
+            .selection(CellSelection::Scanline)
+            .pattern_choice(PatternChoice::MaxWeight)
+            .backtracking(backtracking)
+            .max_snapshots(100);
+        for (p, q) in (0..3).flat_map(|p| (0..3).map(move |q| (p, q))) {
+            let (p_idx, q_idx) = (PatternIndex::new(p), PatternIndex::new(q));
+            if p != q {
+                builder = builder.allow(p_idx, ring, q_idx);
+            }
+            if p != 0 || q != 0 {
+                for spoke in 2..5 {
+                    builder = builder.allow(p_idx, graph.label(spoke), q_idx);
+                }
+            }
+        }
+
+        let mut solver = Solver::new(builder.build().unwrap());
+        let summary = solver.run_until_complete(1);
+        assert_eq!(summary.result, IterationResult::SUCCESS);
+        assert_eq!(solver.observed_at(0, 0), Some(PatternIndex::new(1)));
+        summary.reverts
+    };
+
+    // Once to re-pick node 5, once straight back to the hub.
+    assert_eq!(trap(Backtracking::Backjump), 2);
+    assert!(trap(Backtracking::Trail) > 2);
+
+    // The odd periodic board fails without trying each observation in turn.
+    let config = checkerboard_config(3, 2).periodic_x(true).backtracking(Backtracking::Backjump).build().unwrap();
+    assert_eq!(run_to_end(&mut Solver::new(config)), IterationResult::FAIL);
+}
+
+#[test]
+fn restart_policies_budget_each_attempt() {
+    // An unconstrained 8x8 output always takes 64 steps plus the one reporting SUCCESS.
+    let steps = |policy| {
+        let mut solver = unconstrained_solver(3);
+        let summary = solver.run_with_restarts(policy).unwrap();
+
+        assert_eq!(summary.result, IterationResult::SUCCESS);
+        assert_eq!(summary.restarts + 1, solver.attempt_log().len());
+        solver.attempt_log().iter().map(|a| a.steps).collect::<Vec<_>>()
+    };
+
+    let luby = steps(RestartPolicy::Luby { unit: 10, attempts: 20 });
+    assert_eq!(luby, vec![10, 10, 20, 10, 10, 20, 40, 10, 10, 20, 10, 10, 20, 40, 65]);
+
+    let geometric = steps(RestartPolicy::Geometric {
+        initial: 8,
+        factor: 2.0,
+        attempts: 10,
+    });
+    assert_eq!(geometric, vec![8, 16, 32, 64, 65]);
+    assert_eq!(steps(RestartPolicy::Fixed { attempts: 3 }), vec![65]);
+
+    // Every attempt at the odd periodic board fails, each with its own seed.
+    let failing = || {
+        let config = checkerboard_config(3, 2).periodic_x(true).seed(9).build().unwrap();
+        let mut solver = Solver::new(config);
+        let summary = solver.run_with_restarts(RestartPolicy::Fixed { attempts: 3 }).unwrap();
+
+        assert_eq!(summary.result, IterationResult::FAIL);
+        assert_eq!(summary.restarts, 2);
+        solver.attempt_log().to_vec()
+    };
+
+    let log = failing();
+    assert_eq!(log.len(), 3);
+    assert!(log.iter().all(|a| a.result == IterationResult::FAIL));
+    assert_eq!(log[0].seed, 9);
+    assert!(log[1].seed != log[0].seed && log[2].seed != log[1].seed);
+    assert_eq!(log, failing());
+}
+
+#[test]
+fn restart_policies_reject_shrinking_budgets() {
+    let mut solver = checkerboard_solver(4, 4);
+    let geometric = |initial, factor| RestartPolicy::Geometric {
+        initial,
+        factor,
+        attempts: 4,
+    };
+
+    for &policy in &[geometric(8, f64::NAN), geometric(8, f64::INFINITY), geometric(8, 0.5), geometric(0, 2.0)] {
+        let rejected = solver.run_with_restarts(policy);
+
+        assert!(matches!(rejected, Err(WfcError::InvalidRestartPolicy { .. })));
+        assert!(solver.attempt_log().is_empty());
+    }
+
+    assert!(solver.run_with_restarts(geometric(8, 1.0)).is_ok());
+}
+
+#[test]
+fn snapshot_policies_thin_the_history() {
+    // Every cell of the unconstrained 8x8 output is its own observation, numbered 0 to 63.
+    let solve = |builder: WfcConfigBuilder| {
+        let config = builder
+            .backtracking(Backtracking::Snapshot)
+            .max_snapshots(8)
+            .seed(5)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        solver
+    };
+    let decisions = |builder| solve(builder).snapshot_decisions();
+
+    let every_fourth = decisions(unconstrained_config(8, 8).snapshot_policy(SnapshotPolicy::Decisions(4)));
+    assert_eq!(every_fourth, vec![0, 8, 16, 24, 32, 40, 48, 60]);
+
+    // Dense near the newest observation, sparse towards the first.
+    let exponential = decisions(unconstrained_config(8, 8).snapshot_policy(SnapshotPolicy::Exponential));
+    assert_eq!(exponential, vec![0, 32, 48, 56, 60, 61, 62, 63]);
+
+    // A byte budget keeps fewer snapshots, still spanning the whole run.
+    let mut solver = solve(unconstrained_config(8, 8).max_snapshot_bytes(4000).snapshot_keyframe_interval(1));
+    let kept = solver.snapshot_decisions();
+    assert!(kept.len() < 8);
+    assert_eq!((kept[0], kept[kept.len() - 1]), (0, 63));
+
+    // Tightening the policy thins what is already there.
+    solver.set_snapshot_policy(SnapshotPolicy::Exponential, 1300);
+    assert_eq!(solver.snapshot_decisions(), vec![0, 63]);
+
+    // Reverts still work through a thinned history.
+    let config = checkerboard_config(5, 2)
+        .periodic_x(true)
+        .backtracking(Backtracking::Snapshot)
+        .snapshot_policy(SnapshotPolicy::Exponential)
+        .max_snapshots(2)
+        .build()
+        .unwrap();
+    assert_eq!(run_to_end(&mut Solver::new(config)), IterationResult::FAIL);
+}
+
+#[test]
+fn snapshot_budget_keeps_the_newest_snapshot() {
+    let config = unconstrained_config(8, 8)
+        .backtracking(Backtracking::Snapshot)
+        .snapshot_policy(SnapshotPolicy::Exponential)
+        .max_snapshots(8)
+        .seed(5)
+        .build()
+        .unwrap();
+    let mut solver = Solver::new(config);
+    assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+
+    // Even a budget no snapshot fits in leaves the newest one to revert to.
+    solver.set_snapshot_policy(SnapshotPolicy::Exponential, 1);
+    assert_eq!(solver.snapshot_decisions(), vec![63]);
+    assert!(solver.revert());
+    assert_eq!(solver.filled_count(), 63);
+    assert!(!solver.revert());
+}
+
+#[test]
+fn delta_snapshots_restore_the_same_waves() {
+    // Some observations of the three colors have to be reverted.
+    let solve = |keyframe_interval| {
+        let config = three_colors(12, 12)
+            .backtracking(Backtracking::Snapshot)
+            .snapshot_policy(SnapshotPolicy::Exponential)
+            .snapshot_keyframe_interval(keyframe_interval)
+            .seed(6)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+        let summary = solver.run_until_complete(1);
+
+        assert_eq!(summary.result, IterationResult::SUCCESS);
+        (summary.reverts, solver.observed_patterns(), solver.memory_usage_bytes())
+    };
+
+    let (full_reverts, full_output, full_bytes) = solve(1);
+    let (delta_reverts, delta_output, delta_bytes) = solve(16);
+    assert!(full_reverts > 0);
+    assert_eq!(delta_reverts, full_reverts);
+    assert_eq!(delta_output, full_output);
+    assert!(delta_bytes < full_bytes);
+
+    // Under the same byte budget, deltas keep more of the history.
+    let kept = |keyframe_interval| {
+        let config = unconstrained_config(8, 8)
+            .backtracking(Backtracking::Snapshot)
+            .snapshot_policy(SnapshotPolicy::Exponential)
+            .max_snapshots(64)
+            .max_snapshot_bytes(4000)
+            .snapshot_keyframe_interval(keyframe_interval)
+            .build()
+            .unwrap();
+        let mut solver = Solver::new(config);
+
+        assert_eq!(run_to_end(&mut solver), IterationResult::SUCCESS);
+        solver.snapshot_decisions().len()
+    };
+    assert!(kept(16) > 2 * kept(1));
+}